@@ -1,147 +1,838 @@
-use super::models::{ChatRequest, ChatResponse};
-use anyhow::Result;
-use futures::stream::Stream;
+use super::error::api_error;
+use super::models::{ChatRequest, ChatResponse, StreamEvent};
+use super::providers::{LlmProvider, ProviderRegistry, ProviderRequest};
+use super::retry::{is_retryable_status, is_transient, retry_delay, server_delay};
+use super::sse::SseDecoder;
+use crate::config::manager::{HttpConfig, ModelInfo, RetryConfig};
+use crate::config::ConfigManager;
+use crate::utils::LlmCliError;
+use anyhow::{Context, Result};
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Client;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>;
 
 pub struct LlmClient {
     client: Client,
     api_key: String,
-    provider: String,
+    provider: Arc<dyn LlmProvider>,
     base_url: String,
+    retry: RetryConfig,
+    /// Models to fall over to, in order, with a client for each
+    fallbacks: Vec<(ModelInfo, LlmClient)>,
 }
 
 impl LlmClient {
-    pub fn new(api_key: String, provider: &str) -> Self {
-        let base_url = match provider {
-            "anthropic" => "https://api.anthropic.com/v1",
-            "openai" => "https://api.openai.com/v1",
-            "google" => "https://generativelanguage.googleapis.com/v1beta",
-            _ => "https://api.openai.com/v1",
-        };
+    pub fn new(api_key: String, provider: &str, http: &HttpConfig) -> Result<Self> {
+        let provider = ProviderRegistry::default()
+            .get(provider)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported provider: {}", provider))?;
+
+        Self::with_provider(api_key, provider, http)
+    }
+
+    /// Builds a client for a provider declared under `api.providers` in config.toml,
+    /// honoring its wire protocol, base URL override, API key and HTTP settings.
+    pub fn from_config(config_manager: &ConfigManager, provider: &str) -> Result<Self> {
+        let config = config_manager.get();
+        let provider_config = config_manager.get_provider_config(provider)?;
+        let api_key = config_manager.get_api_key(provider)?;
+        let http = config.http.merged(&provider_config.http);
+
+        let mut client = Self::new(api_key, provider_config.protocol(provider), &http)?;
+        if let Some(base_url) = &provider_config.base_url {
+            client.base_url = base_url.trim_end_matches('/').to_string();
+        }
+        client.retry = config.retry.clone();
+
+        Ok(client)
+    }
 
-        Self {
-            client: Client::new(),
+    /// Builds a client for a fallback chain as resolved by
+    /// `ConfigManager::resolve_models`: requests go to the first model and
+    /// fall over to the next one on rate limits, outages and context-length
+    /// errors. Requests should name the first model.
+    ///
+    /// A fallback gets the same messages, system prompt and temperature as the
+    /// first model, but no longer a reply than its own `max_tokens` allows.
+    pub fn for_models(config_manager: &ConfigManager, models: &[&ModelInfo]) -> Result<Self> {
+        let (primary, fallbacks) = models
+            .split_first()
+            .context("A fallback chain needs at least one model")?;
+
+        let mut client = Self::from_config(config_manager, &primary.provider)?;
+        for model in fallbacks {
+            let fallback = Self::from_config(config_manager, &model.provider)?;
+            client.fallbacks.push(((*model).clone(), fallback));
+        }
+
+        Ok(client)
+    }
+
+    pub fn with_provider(
+        api_key: String,
+        provider: Arc<dyn LlmProvider>,
+        http: &HttpConfig,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: http_client(http)?,
             api_key,
-            provider: provider.to_string(),
-            base_url: base_url.to_string(),
+            base_url: provider.base_url().to_string(),
+            provider,
+            retry: RetryConfig::default(),
+            fallbacks: Vec::new(),
+        })
+    }
+
+    pub async fn chat(&self, mut request: ChatRequest) -> Result<ChatResponse> {
+        request.stream = Some(false);
+
+        let mut result = self.chat_once(&request).await;
+        let mut failed = self;
+        for (model, client) in &self.fallbacks {
+            match &result {
+                Err(e) if falls_over(e) => failed.warn_fallback(&request.model, e, &model.name),
+                _ => break,
+            }
+            request.model = model.name.clone();
+            request.max_completion_tokens = model.reply_limit(request.max_completion_tokens);
+            result = client.chat_once(&request).await;
+            failed = client;
         }
+        result
     }
 
-    pub async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+    async fn chat_once(&self, request: &ChatRequest) -> Result<ChatResponse> {
         log::info!(
             "Sending chat request to {} with model {}",
-            self.provider,
+            self.provider.name(),
             request.model
         );
 
-        let (url, headers, body) = match self.provider.as_str() {
-            "openai" => self.build_openai_request(&request),
-            "anthropic" => self.build_anthropic_request(&request),
-            "google" => self.build_google_request(&request),
-            _ => anyhow::bail!("Unsupported provider: {}", self.provider),
-        };
-
-        let response = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .json(&body)
-            .send()
-            .await?;
+        let response = self.send(request).await?;
 
         let status = response.status();
         let raw_body = response.text().await?;
 
         if !status.is_success() {
-            anyhow::bail!("API error (Status {}): {}", status, raw_body);
+            return Err(api_error(self.provider.name(), Some(status), &raw_body).into());
         }
 
-        let chat_response: ChatResponse = serde_json::from_str(&raw_body)
-            .map_err(|e| anyhow::anyhow!("JSON Decode Error: {}. \nRaw Body: {}", e, raw_body))?;
+        let mut response = self.provider.parse_response(&raw_body)?;
+        response.model = request.model.clone();
+        Ok(response)
+    }
 
-        Ok(chat_response)
+    /// Streams the response, starting with a `StreamEvent::Model` naming the
+    /// model that answers. Only failures before the first token can fall over.
+    pub async fn chat_stream(&self, mut request: ChatRequest) -> Result<EventStream> {
+        request.stream = Some(true);
+
+        let mut result = self.open_stream(&request).await;
+        let mut failed = self;
+        for (model, client) in &self.fallbacks {
+            match &result {
+                Err(e) if falls_over(e) => failed.warn_fallback(&request.model, e, &model.name),
+                _ => break,
+            }
+            request.model = model.name.clone();
+            request.max_completion_tokens = model.reply_limit(request.max_completion_tokens);
+            result = client.open_stream(&request).await;
+            failed = client;
+        }
+
+        let model = stream::once(async move { Ok(StreamEvent::Model(request.model)) });
+        Ok(Box::pin(model.chain(result?)))
     }
 
-    fn build_openai_request(
-        &self,
-        request: &ChatRequest,
-    ) -> (String, reqwest::header::HeaderMap, serde_json::Value) {
-        let url = format!("{}/chat/completions", self.base_url);
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            format!("Bearer {}", self.api_key).parse().unwrap(),
+    async fn open_stream(&self, request: &ChatRequest) -> Result<EventStream> {
+        log::info!(
+            "Streaming chat request to {} with model {}",
+            self.provider.name(),
+            request.model
         );
 
-        let body = serde_json::json!({
-            "model": request.model,
-            "messages": request.messages,
-            "max_completion_tokens": request.max_completion_tokens,
-            "temperature": request.temperature,
-            "stream": request.stream
+        let response = self.send(request).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let raw_body = response.text().await?;
+            return Err(api_error(self.provider.name(), Some(status), &raw_body).into());
+        }
+
+        let provider = self.provider.clone();
+        let mut decoder = SseDecoder::new();
+
+        let events = response.bytes_stream().flat_map(move |chunk| {
+            let items: Vec<Result<StreamEvent>> = match chunk {
+                Ok(bytes) => decoder
+                    .feed(&bytes)
+                    .into_iter()
+                    .flat_map(|event| match provider.parse_stream_event(&event.data) {
+                        Ok(events) => events.into_iter().map(Ok).collect(),
+                        Err(e) => vec![Err(e)],
+                    })
+                    .collect(),
+                Err(e) => vec![Err(e.into())],
+            };
+            stream::iter(items)
         });
 
-        (url, headers, body)
+        Ok(Box::pin(events))
     }
 
-    fn build_anthropic_request(
-        &self,
-        request: &ChatRequest,
-    ) -> (String, reqwest::header::HeaderMap, serde_json::Value) {
-        let url = format!("{}/messages", self.base_url);
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("x-api-key", self.api_key.parse().unwrap());
-        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+    /// Logs that `model`, sent to this client's provider, failed with `error`
+    /// and `next` is tried instead.
+    fn warn_fallback(&self, model: &str, error: &anyhow::Error, next: &str) {
+        log::warn!(
+            "{} request for {} failed ({}); falling back to {}",
+            self.provider.name(),
+            model,
+            error,
+            next
+        );
+    }
 
-        let body = serde_json::json!({
-            "model": request.model,
-            "messages": request.messages,
-        "max_completion_tokens": request.max_completion_tokens,
-            "temperature": request.temperature,
-            "stream": request.stream
-        });
+    /// Posts the request, retrying rate limits, server errors and dropped
+    /// connections as configured under `[retry]`. Other failures, and the
+    /// last attempt's response, are returned as they are.
+    async fn send(&self, request: &ChatRequest) -> Result<reqwest::Response> {
+        let ProviderRequest { url, headers, body } =
+            self.provider
+                .build_request(&self.base_url, &self.api_key, request)?;
+
+        let mut attempt = 1;
+        loop {
+            let outcome = self
+                .client
+                .post(&url)
+                .headers(headers.clone())
+                .json(&body)
+                .send()
+                .await;
+
+            let (reason, requested_delay) = match &outcome {
+                Ok(response) if is_retryable_status(response.status()) => (
+                    format!("status {}", response.status()),
+                    server_delay(response.headers()),
+                ),
+                Err(e) if is_transient(e) => (e.to_string(), None),
+                _ => return Ok(outcome?),
+            };
+            if attempt >= self.retry.max_attempts {
+                return Ok(outcome?);
+            }
+
+            let delay = retry_delay(&self.retry, attempt, requested_delay);
+            log::warn!(
+                "{} request failed ({}); retrying in {:?} (attempt {}/{})",
+                self.provider.name(),
+                reason,
+                delay,
+                attempt + 1,
+                self.retry.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Whether another model might succeed where this one failed: on rate
+/// limits, outages and prompts too long for its context window, but not on
+/// problems like a bad key that the next model would report too.
+fn falls_over(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<LlmCliError>() {
+        Some(error) => matches!(
+            error,
+            LlmCliError::RateLimited { .. }
+                | LlmCliError::Overloaded { .. }
+                | LlmCliError::ContextLengthExceeded { .. }
+        ),
+        None => error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(is_transient),
+    }
+}
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 300;
+
+/// Builds the underlying HTTP client. Unset timeouts fall back to the
+/// defaults above; a timeout of 0 disables it.
+fn http_client(http: &HttpConfig) -> Result<Client> {
+    let secs = |value: Option<u64>, default: Option<u64>| {
+        value
+            .or(default)
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+    };
+
+    let mut builder = Client::builder();
+    if let Some(timeout) = secs(
+        http.connect_timeout_secs,
+        Some(DEFAULT_CONNECT_TIMEOUT_SECS),
+    ) {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = secs(http.read_timeout_secs, Some(DEFAULT_READ_TIMEOUT_SECS)) {
+        builder = builder.read_timeout(timeout);
+    }
+    if let Some(timeout) = secs(http.timeout_secs, None) {
+        builder = builder.timeout(timeout);
+    }
+
+    if let Some(proxy) = &http.proxy {
+        let proxy =
+            reqwest::Proxy::all(proxy).with_context(|| format!("Invalid proxy URL: {}", proxy))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &http.ca_bundle {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if !http.headers.is_empty() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &http.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name: {}", name))?;
+            let value = HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value for header {}", name))?;
+            headers.insert(name, value);
+        }
+        builder = builder.default_headers(headers);
+    }
+
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::models::{FinishReason, Message, Usage};
+
+    fn client_for(provider: &str, base_url: String) -> LlmClient {
+        let mut client =
+            LlmClient::new("test-key".to_string(), provider, &HttpConfig::default()).unwrap();
+        client.base_url = base_url;
+        client
+    }
+
+    fn request(model: &str) -> ChatRequest {
+        ChatRequest {
+            model: model.to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: "Hi".to_string(),
+            }],
+            max_completion_tokens: 16,
+            temperature: None,
+            stream: None,
+        }
+    }
+
+    fn model(name: &str, provider: &str, max_tokens: Option<u32>) -> ModelInfo {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "provider": provider,
+            "display_name": name,
+            "max_tokens": max_tokens,
+        }))
+        .unwrap()
+    }
+
+    async fn collect(client: &LlmClient, request: ChatRequest) -> Vec<String> {
+        let mut stream = client.chat_stream(request).await.unwrap();
+        let mut tokens = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let StreamEvent::Text(token) = event.unwrap() {
+                tokens.push(token);
+            }
+        }
+        tokens
+    }
+
+    async fn assemble(client: &LlmClient, request: ChatRequest) -> ChatResponse {
+        let mut stream = client.chat_stream(request).await.unwrap();
+        let mut response = ChatResponse::default();
+        while let Some(event) = stream.next().await {
+            response.apply(event.unwrap());
+        }
+        response
+    }
+
+    #[tokio::test]
+    async fn test_openai_stream() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"stream": true}),
+            ))
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+                "data: [DONE]\n\n",
+            ))
+            .create_async()
+            .await;
+
+        let client = client_for("openai", server.url());
+        assert_eq!(collect(&client, request("gpt-4o")).await, vec!["Hel", "lo"]);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_stream() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/messages")
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "event: message_start\n",
+                "data: {\"type\":\"message_start\",\"message\":{}}\n\n",
+                "event: ping\n",
+                "data: {\"type\":\"ping\"}\n\n",
+                "event: content_block_delta\n",
+                "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi there\"}}\n\n",
+                "event: message_stop\n",
+                "data: {\"type\":\"message_stop\"}\n\n",
+            ))
+            .create_async()
+            .await;
+
+        let client = client_for("anthropic", server.url());
+        assert_eq!(
+            collect(&client, request("claude-3-haiku-20240307")).await,
+            vec!["Hi there"]
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_google_stream() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/models/gemini-pro:streamGenerateContent")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "alt".to_string(),
+                "sse".to_string(),
+            ))
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"One \"}]}}]}\r\n\r\n",
+                "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"two\"}]}}]}\r\n\r\n",
+            ))
+            .create_async()
+            .await;
+
+        let client = client_for("google", server.url());
+        assert_eq!(
+            collect(&client, request("gemini-pro")).await,
+            vec!["One ", "two"]
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_chat_skips_non_text_blocks() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/messages")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"max_tokens": 16}),
+            ))
+            .with_body(
+                r#"{"id":"msg_1","content":[{"type":"thinking","thinking":"hmm"},{"type":"text","text":"Answer"}]}"#,
+            )
+            .create_async()
+            .await;
 
-        (url, headers, body)
+        let client = client_for("anthropic", server.url());
+        let response = client
+            .chat(request("claude-3-haiku-20240307"))
+            .await
+            .unwrap();
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(response.get_text(), "Answer");
     }
 
-    fn build_google_request(
-        &self,
-        request: &ChatRequest,
-    ) -> (String, reqwest::header::HeaderMap, serde_json::Value) {
-        let url = format!(
-            "{}/models/{}:generateContent?key={}",
-            self.base_url, request.model, self.api_key
+    #[tokio::test]
+    async fn test_openai_usage_and_finish_reason() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"stream": true, "stream_options": {"include_usage": true}}),
+            ))
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":16,\"prompt_tokens_details\":{\"cached_tokens\":4},\"completion_tokens_details\":{\"reasoning_tokens\":6}}}\n\n",
+                "data: [DONE]\n\n",
+            ))
+            .create_async()
+            .await;
+
+        let client = client_for("openai", server.url());
+        let response = assemble(&client, request("gpt-4o")).await;
+        assert_eq!(response.text, "Hi");
+        assert_eq!(response.finish_reason, Some(FinishReason::Length));
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input: 12,
+                output: 16,
+                cached: 4,
+                reasoning: 6
+            })
         );
-        let headers = reqwest::header::HeaderMap::new();
+    }
 
-        // Convert messages to Gemini format
-        let contents = request
-            .messages
+    #[tokio::test]
+    async fn test_anthropic_stream_usage_across_frames() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/messages")
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":20,\"cache_read_input_tokens\":100,\"output_tokens\":1}}}\n\n",
+                "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Done\"}}\n\n",
+                "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":9}}\n\n",
+            ))
+            .create_async()
+            .await;
+
+        let client = client_for("anthropic", server.url());
+        let response = assemble(&client, request("claude-3-haiku-20240307")).await;
+        assert_eq!(response.finish_reason, Some(FinishReason::Stop));
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input: 120,
+                output: 9,
+                cached: 100,
+                reasoning: 0
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_translation() {
+        let conversation = |model: &str| {
+            let mut request = request(model);
+            request.messages = [
+                ("system", "Be brief"),
+                ("user", "Hi"),
+                ("assistant", "Hello"),
+            ]
             .iter()
-            .map(|msg| {
-                serde_json::json!({
-                    "parts": [{"text": msg.content}],
-                    "role": if msg.role == "user" { "user" } else { "model" }
-                })
+            .map(|(role, content)| Message {
+                role: role.to_string(),
+                content: content.to_string(),
             })
-            .collect::<Vec<_>>();
+            .collect();
+            request
+        };
 
-        let body = serde_json::json!({
-            "contents": contents,
-            "generationConfig": {
-                "temperature": request.temperature,
-                "maxOutputTokens": request.max_completion_tokens
-            }
-        });
+        let mut server = mockito::Server::new_async().await;
+        let openai = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "messages": [
+                    {"role": "system", "content": "Be brief"},
+                    {"role": "user", "content": "Hi"},
+                    {"role": "assistant", "content": "Hello"}
+                ]
+            })))
+            .with_body(r#"{"id":"c1","choices":[{"message":{"content":"ok"}}]}"#)
+            .create_async()
+            .await;
+        let anthropic = server
+            .mock("POST", "/messages")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "system": "Be brief",
+                "messages": [
+                    {"role": "user", "content": "Hi"},
+                    {"role": "assistant", "content": "Hello"}
+                ]
+            })))
+            .with_body(r#"{"id":"m1","content":[{"type":"text","text":"ok"}]}"#)
+            .create_async()
+            .await;
+        let google = server
+            .mock("POST", "/models/gemini-pro:generateContent")
+            .match_query(mockito::Matcher::Any)
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "systemInstruction": {"parts": [{"text": "Be brief"}]},
+                "contents": [
+                    {"role": "user", "parts": [{"text": "Hi"}]},
+                    {"role": "model", "parts": [{"text": "Hello"}]}
+                ]
+            })))
+            .with_body(r#"{"candidates":[{"content":{"parts":[{"text":"ok"}]}}]}"#)
+            .create_async()
+            .await;
 
-        (url, headers, body)
+        for (provider, model) in [
+            ("openai", "gpt-4o"),
+            ("anthropic", "claude"),
+            ("google", "gemini-pro"),
+        ] {
+            let client = client_for(provider, server.url());
+            let response = client.chat(conversation(model)).await.unwrap();
+            assert_eq!(response.text, "ok");
+        }
+        openai.assert_async().await;
+        anthropic.assert_async().await;
+        google.assert_async().await;
     }
-    pub async fn chat_stream(
-        &self,
-        _request: ChatRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
-        // TODO: Implement streaming
-        todo!("Streaming implementation")
+
+    #[tokio::test]
+    async fn test_google_chat_usage() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/models/gemini-pro:generateContent")
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                r#"{"responseId":"r1","candidates":[{"content":{"parts":[{"text":"Hey"}]},"finishReason":"SAFETY"}],"usageMetadata":{"promptTokenCount":5,"candidatesTokenCount":3,"thoughtsTokenCount":7}}"#,
+            )
+            .create_async()
+            .await;
+
+        let client = client_for("google", server.url());
+        let response = client.chat(request("gemini-pro")).await.unwrap();
+        assert_eq!(response.finish_reason, Some(FinishReason::ContentFilter));
+        assert_eq!(
+            response.usage,
+            Some(Usage {
+                input: 5,
+                output: 10,
+                cached: 0,
+                reasoning: 7
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limit_then_succeeds() {
+        let mut server = mockito::Server::new_async().await;
+        let limited = server
+            .mock("POST", "/chat/completions")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/chat/completions")
+            .with_body(r#"{"id":"c1","choices":[{"message":{"content":"Done"}}]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = client_for("openai", server.url());
+        let response = client.chat(request("gpt-4o")).await.unwrap();
+        assert_eq!(response.text, "Done");
+        limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_falls_over_to_next_model() {
+        let mut server = mockito::Server::new_async().await;
+        let overloaded = server
+            .mock("POST", "/chat/completions")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        let fallback = server
+            .mock("POST", "/messages")
+            // The fallback's own reply limit is lower than the request's
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"model": "claude", "max_tokens": 8}),
+            ))
+            .with_body(r#"{"id":"m1","content":[{"type":"text","text":"Covered"}]}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let mut client = client_for("openai", server.url());
+        client.retry.max_attempts = 1;
+        client.fallbacks.push((
+            model("claude", "anthropic", Some(8)),
+            client_for("anthropic", server.url()),
+        ));
+
+        let response = client.chat(request("gpt-4o")).await.unwrap();
+        assert_eq!(response.text, "Covered");
+        assert_eq!(response.model, "claude");
+
+        let mut stream = client.chat_stream(request("gpt-4o")).await.unwrap();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first, StreamEvent::Model("claude".to_string()));
+        overloaded.assert_async().await;
+        fallback.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_auth_errors_do_not_fall_over() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/chat/completions")
+            .with_status(401)
+            .create_async()
+            .await;
+        let fallback = server
+            .mock("POST", "/messages")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let mut client = client_for("openai", server.url());
+        client.fallbacks.push((
+            model("claude", "anthropic", None),
+            client_for("anthropic", server.url()),
+        ));
+
+        assert!(client.chat(request("gpt-4o")).await.is_err());
+        fallback.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts_and_skips_auth_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("POST", "/chat/completions")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let mut client = client_for("openai", server.url());
+        client.retry.initial_delay_ms = 1;
+        let err = client.chat(request("gpt-4o")).await.unwrap_err();
+        assert!(err.to_string().contains("503"));
+        unavailable.assert_async().await;
+
+        let unauthorized = server
+            .mock("POST", "/messages")
+            .with_status(401)
+            .expect(1)
+            .create_async()
+            .await;
+        let client = client_for("anthropic", server.url());
+        assert!(client.chat(request("claude")).await.is_err());
+        unauthorized.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_http_settings_apply_headers_and_read_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/chat/completions")
+            .match_header("x-team", "research")
+            .with_chunked_body(|writer| {
+                std::thread::sleep(Duration::from_secs(3));
+                writer.write_all(b"{}")
+            })
+            .create_async()
+            .await;
+
+        let http = HttpConfig {
+            read_timeout_secs: Some(1),
+            headers: [("x-team".to_string(), "research".to_string())].into(),
+            ..Default::default()
+        };
+        let mut client = LlmClient::new("key".to_string(), "openai", &http).unwrap();
+        client.base_url = server.url();
+        client.retry.max_attempts = 1;
+
+        let started = std::time::Instant::now();
+        let err = client.chat(request("gpt-4o")).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!(
+            err.downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_timeout()),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_invalid_http_settings_are_rejected() {
+        let http = HttpConfig {
+            headers: [("bad header".to_string(), "x".to_string())].into(),
+            ..Default::default()
+        };
+        assert!(http_client(&http).is_err());
+
+        let http = HttpConfig {
+            ca_bundle: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        };
+        assert!(http_client(&http).is_err());
+    }
+
+    #[test]
+    fn test_unknown_provider_is_rejected() {
+        assert!(LlmClient::new("key".to_string(), "nope", &HttpConfig::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/chat/completions")
+            .with_status(401)
+            .with_body("{\"error\":{\"message\":\"bad key\"}}")
+            .create_async()
+            .await;
+
+        let client = client_for("openai", server.url());
+        let err = client.chat_stream(request("gpt-4o")).await.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<LlmCliError>(),
+            Some(LlmCliError::AuthFailed { message, .. }) if message == "bad key"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_event_is_typed() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/messages")
+            .with_header("content-type", "text/event-stream")
+            .with_body(
+                "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+            )
+            .create_async()
+            .await;
+
+        let client = client_for("anthropic", server.url());
+        let mut stream = client.chat_stream(request("claude")).await.unwrap();
+        let model = stream.next().await.unwrap().unwrap();
+        assert_eq!(model, StreamEvent::Model("claude".to_string()));
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LlmCliError>()
+                .map(LlmCliError::exit_code),
+            Some(10)
+        );
     }
 }
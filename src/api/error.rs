@@ -0,0 +1,151 @@
+use crate::utils::LlmCliError;
+use reqwest::StatusCode;
+
+/// Turns a provider's error reply into a typed error.
+///
+/// OpenAI, Anthropic and Gemini all wrap failures as `{"error": {...}}` with a
+/// `message` and some machine-readable code (`code`, `type` or `status`), so
+/// one reader covers them. `status` is `None` for errors sent mid-stream.
+pub fn api_error(provider: &str, status: Option<StatusCode>, body: &str) -> LlmCliError {
+    let (code, message) = error_details(body);
+    let message = match (message.is_empty(), status) {
+        (true, Some(status)) => status.to_string(),
+        _ => message,
+    };
+    let provider = provider.to_string();
+
+    let lower = message.to_lowercase();
+    let code_has = |needles: &[&str]| needles.iter().any(|needle| code.contains(needle));
+    let says = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+    let status = status.map(|status| status.as_u16());
+
+    // Most specific first: a too-long prompt or a missing model is usually a
+    // 400 or 404 that would otherwise read as a generic failure
+    if code_has(&["context_length"])
+        || says(&[
+            "context length",
+            "context window",
+            "prompt is too long",
+            "maximum number of tokens",
+            "too many tokens",
+        ])
+    {
+        LlmCliError::ContextLengthExceeded { provider, message }
+    } else if code_has(&["content_filter", "content_policy", "safety"])
+        || says(&["content management policy", "safety system"])
+    {
+        LlmCliError::ContentFiltered { provider, message }
+    } else if code_has(&["model_not_found"])
+        || (says(&["model"]) && (status == Some(404) || code_has(&["not_found"])))
+    {
+        LlmCliError::ModelNotFound { provider, message }
+    } else if matches!(status, Some(401 | 403))
+        || code_has(&[
+            "invalid_api_key",
+            "authentication",
+            "permission",
+            "unauthenticated",
+        ])
+        || says(&["api key not valid", "invalid api key", "incorrect api key"])
+    {
+        LlmCliError::AuthFailed { provider, message }
+    } else if status == Some(429)
+        || code_has(&["rate_limit", "resource_exhausted", "insufficient_quota"])
+    {
+        LlmCliError::RateLimited { provider, message }
+    } else if matches!(status, Some(502..=504 | 529)) || code_has(&["overloaded", "unavailable"]) {
+        LlmCliError::Overloaded { provider, message }
+    } else {
+        let message = match status {
+            Some(status) => format!("{} returned status {}: {}", provider, status, message),
+            None => format!("{}: {}", provider, message),
+        };
+        LlmCliError::ApiError(message)
+    }
+}
+
+/// The lowercased error codes and the human-readable message of an error
+/// body; a body that is not JSON is its own message.
+fn error_details(body: &str) -> (String, String) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return (String::new(), body.trim().to_string());
+    };
+    let error = value.get("error").unwrap_or(&value);
+
+    let code = ["code", "type", "status"]
+        .iter()
+        .filter_map(|key| match error.get(key)? {
+            serde_json::Value::String(s) => Some(s.to_lowercase()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let message = match error.get("message").and_then(|m| m.as_str()) {
+        Some(message) => message.to_string(),
+        None if error.is_string() => error.as_str().unwrap_or_default().to_string(),
+        None => body.trim().to_string(),
+    };
+
+    (code, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_provider_error_bodies() {
+        let openai = r#"{"error": {"message": "This model's maximum context length is 128000 tokens.", "type": "invalid_request_error", "code": "context_length_exceeded"}}"#;
+        assert!(matches!(
+            api_error("openai", Some(StatusCode::BAD_REQUEST), openai),
+            LlmCliError::ContextLengthExceeded { ref message, .. } if message.starts_with("This model's")
+        ));
+
+        let anthropic =
+            r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+        assert!(matches!(
+            api_error("anthropic", StatusCode::from_u16(529).ok(), anthropic),
+            LlmCliError::Overloaded { .. }
+        ));
+        assert!(matches!(
+            api_error("anthropic", None, anthropic),
+            LlmCliError::Overloaded { .. }
+        ));
+
+        let google = r#"{"error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "status": "INVALID_ARGUMENT"}}"#;
+        assert!(matches!(
+            api_error("google", Some(StatusCode::BAD_REQUEST), google),
+            LlmCliError::AuthFailed { .. }
+        ));
+
+        let missing = r#"{"error": {"message": "The model `gpt-9` does not exist", "code": "model_not_found"}}"#;
+        assert!(matches!(
+            api_error("openai", Some(StatusCode::NOT_FOUND), missing),
+            LlmCliError::ModelNotFound { .. }
+        ));
+
+        let filtered =
+            r#"{"error": {"message": "The response was filtered", "code": "content_filter"}}"#;
+        assert!(matches!(
+            api_error("openai", Some(StatusCode::BAD_REQUEST), filtered),
+            LlmCliError::ContentFiltered { .. }
+        ));
+
+        assert!(matches!(
+            api_error("openai", Some(StatusCode::TOO_MANY_REQUESTS), ""),
+            LlmCliError::RateLimited { ref message, .. } if message == "429 Too Many Requests"
+        ));
+    }
+
+    #[test]
+    fn test_unrecognized_errors_keep_status_and_body() {
+        let error = api_error("local", Some(StatusCode::IM_A_TEAPOT), "short and stout");
+        assert!(matches!(error, LlmCliError::ApiError(_)));
+        assert_eq!(
+            error.to_string(),
+            "API error: local returned status 418: short and stout"
+        );
+    }
+}
@@ -1,6 +1,9 @@
+mod error;
 pub mod models;
 mod providers;
+mod retry;
+mod sse;
 
 pub use client::LlmClient;
 pub mod client;
-pub use models::{ChatRequest, Message};
\ No newline at end of file
+pub use models::{ChatRequest, ChatResponse, FinishReason, Message, StreamEvent, Usage};
@@ -17,91 +17,129 @@ pub struct ChatRequest {
     pub stream: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Provider-neutral response, normalized by each `LlmProvider` implementation.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ChatResponse {
-    // Uses alias to handle both OpenAI's "id" and Gemini's "responseId"
-    #[serde(alias = "responseId")]
     pub id: String,
-    
-    // Anthropic format
-    pub content: Option<Vec<ContentBlock>>,
-    
-    // OpenAI format
-    pub choices: Option<Vec<Choice>>,
-    
-    // Google Gemini format
-    pub candidates: Option<Vec<GeminiCandidate>>,
+    pub text: String,
+    pub usage: Option<Usage>,
+    pub finish_reason: Option<FinishReason>,
+    /// The configured model that answered, filled in by `LlmClient`; with a
+    /// fallback chain it may not be the one that was asked
+    #[serde(default)]
+    pub model: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GeminiCandidate {
-    pub content: GeminiContent,
-}
+impl ChatResponse {
+    pub fn get_text(&self) -> String {
+        self.text.clone()
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct GeminiContent {
-    pub parts: Vec<GeminiPart>,
+    /// Folds one streamed event into the response assembled so far.
+    pub fn apply(&mut self, event: StreamEvent) {
+        match event {
+            StreamEvent::Text(text) => self.text.push_str(&text),
+            StreamEvent::Usage(usage) => {
+                self.usage.get_or_insert_with(Usage::default).merge(&usage)
+            }
+            StreamEvent::Finish(reason) => self.finish_reason = Some(reason),
+            StreamEvent::Model(model) => self.model = model,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GeminiPart {
-    // Made optional because Gemini 3 might send a part with only a thoughtSignature
-    pub text: Option<String>,
-    #[serde(rename = "thoughtSignature")]
-    pub thought_signature: Option<String>,
+/// Token counts for one response, normalized across providers.
+///
+/// `input` includes `cached` prompt tokens and `output` includes `reasoning`
+/// tokens, matching how providers bill them.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub input: u64,
+    pub output: u64,
+    #[serde(default)]
+    pub cached: u64,
+    #[serde(default)]
+    pub reasoning: u64,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ContentBlock {
-    #[serde(rename = "type")]
-    pub content_type: String,
-    pub text: Option<String>,
+impl Usage {
+    /// Combines counts reported across several stream frames. Providers report
+    /// running totals, so the largest value seen for each count wins.
+    pub fn merge(&mut self, other: &Usage) {
+        self.input = self.input.max(other.input);
+        self.output = self.output.max(other.output);
+        self.cached = self.cached.max(other.cached);
+        self.reasoning = self.reasoning.max(other.reasoning);
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Choice {
-    pub message: ResponseMessage,
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in", self.input)?;
+        if self.cached > 0 {
+            write!(f, " ({} cached)", self.cached)?;
+        }
+        write!(f, ", {} out", self.output)?;
+        if self.reasoning > 0 {
+            write!(f, " ({} reasoning)", self.reasoning)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ResponseMessage {
-    pub content: String,
+/// Why the model stopped generating.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum FinishReason {
+    /// The model finished its answer or hit a stop sequence
+    Stop,
+    /// The answer was cut off by the token limit
+    Length,
+    /// The provider withheld or truncated the answer for safety reasons
+    ContentFilter,
+    /// The model stopped to call a tool
+    ToolCalls,
+    /// A provider-specific reason, kept verbatim
+    Other(String),
 }
 
-impl ChatResponse {
-    pub fn get_text(&self) -> String {
-        // 1. Try Anthropic path
-        if let Some(content) = &self.content {
-            return content.iter()
-                .filter_map(|block| block.text.as_ref())
-                .cloned()
-                .collect::<Vec<_>>()
-                .join("");
+impl std::fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FinishReason::Stop => write!(f, "stop"),
+            FinishReason::Length => write!(f, "length"),
+            FinishReason::ContentFilter => write!(f, "content_filter"),
+            FinishReason::ToolCalls => write!(f, "tool_calls"),
+            FinishReason::Other(reason) => write!(f, "{}", reason),
         }
+    }
+}
 
-        // 2. Try OpenAI path
-        if let Some(choices) = &self.choices {
-            if let Some(choice) = choices.first() {
-                return choice.message.content.clone();
-            }
-        }
+impl From<FinishReason> for String {
+    fn from(reason: FinishReason) -> Self {
+        reason.to_string()
+    }
+}
 
-        // 3. Try Google Gemini path (Gemini 3 Compatible)
-        if let Some(candidates) = &self.candidates {
-            if let Some(candidate) = candidates.first() {
-                // We join ALL parts because Gemini 3 often splits thoughts and text
-                let text = candidate.content.parts.iter()
-                    .filter_map(|part| part.text.as_ref())
-                    .cloned()
-                    .collect::<Vec<_>>()
-                    .join("");
-                
-                if !text.is_empty() {
-                    return text;
-                }
-            }
+impl From<String> for FinishReason {
+    fn from(reason: String) -> Self {
+        match reason.as_str() {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            "content_filter" => FinishReason::ContentFilter,
+            "tool_calls" => FinishReason::ToolCalls,
+            _ => FinishReason::Other(reason),
         }
-
-        "Error: No response content found".to_string()
     }
-}
\ No newline at end of file
+}
+
+/// One item of a streamed response.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Text(String),
+    /// Token counts so far; a stream may report them in several frames
+    Usage(Usage),
+    Finish(FinishReason),
+    /// The model answering; `LlmClient` sends it before anything else
+    Model(String),
+}
@@ -0,0 +1,146 @@
+use super::{parse_json, parse_stream_json, split_system, LlmProvider, ProviderRequest};
+use crate::api::models::{ChatRequest, ChatResponse, FinishReason, StreamEvent, Usage};
+use anyhow::Result;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+
+pub struct AnthropicProvider;
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    id: String,
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    content_type: String,
+    text: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+}
+
+impl From<AnthropicUsage> for Usage {
+    fn from(usage: AnthropicUsage) -> Self {
+        // `input_tokens` excludes cache reads and writes; count them as input too
+        Usage {
+            input: usage.input_tokens
+                + usage.cache_read_input_tokens
+                + usage.cache_creation_input_tokens,
+            output: usage.output_tokens,
+            cached: usage.cache_read_input_tokens,
+            reasoning: 0,
+        }
+    }
+}
+
+fn finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
+fn usage_event(value: &serde_json::Value) -> Result<Option<StreamEvent>> {
+    if !value.is_object() {
+        return Ok(None);
+    }
+    let usage: AnthropicUsage = serde_json::from_value(value.clone())?;
+    Ok(Some(StreamEvent::Usage(usage.into())))
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn base_url(&self) -> &str {
+        "https://api.anthropic.com/v1"
+    }
+
+    fn build_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        request: &ChatRequest,
+    ) -> Result<ProviderRequest> {
+        let url = format!("{}/messages", base_url);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", api_key.parse()?);
+        headers.insert("anthropic-version", "2023-06-01".parse()?);
+
+        // Anthropic rejects system messages; the prompt goes in a top-level field
+        let (system, messages) = split_system(&request.messages);
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": messages,
+            "max_tokens": request.max_completion_tokens,
+            "temperature": request.temperature,
+            "stream": request.stream
+        });
+        if let Some(system) = system {
+            body["system"] = system.into();
+        }
+
+        Ok(ProviderRequest { url, headers, body })
+    }
+
+    fn parse_response(&self, body: &str) -> Result<ChatResponse> {
+        let response: AnthropicResponse = parse_json(body)?;
+        // Skip thinking and tool-use blocks; only text blocks belong in the answer
+        let text = response
+            .content
+            .iter()
+            .filter(|block| block.content_type == "text")
+            .filter_map(|block| block.text.as_deref())
+            .collect::<String>();
+
+        Ok(ChatResponse {
+            id: response.id,
+            text,
+            usage: response.usage.map(Usage::from),
+            finish_reason: response.stop_reason.as_deref().map(finish_reason),
+            ..Default::default()
+        })
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Vec<StreamEvent>> {
+        let value = parse_stream_json(self.name(), data)?;
+        let mut events = Vec::new();
+
+        match value["type"].as_str() {
+            Some("content_block_delta") => {
+                if let Some(text) = value["delta"]["text"].as_str().filter(|t| !t.is_empty()) {
+                    events.push(StreamEvent::Text(text.to_string()));
+                }
+            }
+            // Input tokens are reported up front, output tokens with the stop reason
+            Some("message_start") => events.extend(usage_event(&value["message"]["usage"])?),
+            Some("message_delta") => {
+                if let Some(reason) = value["delta"]["stop_reason"].as_str() {
+                    events.push(StreamEvent::Finish(finish_reason(reason)));
+                }
+                events.extend(usage_event(&value["usage"])?);
+            }
+            _ => {}
+        }
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,188 @@
+use super::{parse_json, parse_stream_json, split_system, LlmProvider, ProviderRequest};
+use crate::api::models::{ChatRequest, ChatResponse, FinishReason, StreamEvent, Usage};
+use anyhow::Result;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+
+pub struct GoogleProvider;
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponse {
+    #[serde(rename = "responseId", default)]
+    response_id: String,
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    #[serde(rename = "usageMetadata")]
+    usage_metadata: Option<GeminiUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiCandidate {
+    content: Option<GeminiContent>,
+    #[serde(rename = "finishReason")]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsage {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+    #[serde(default)]
+    cached_content_token_count: u64,
+    #[serde(default)]
+    thoughts_token_count: u64,
+}
+
+impl From<&GeminiUsage> for Usage {
+    fn from(usage: &GeminiUsage) -> Self {
+        // Thinking tokens are billed as output but reported separately
+        Usage {
+            input: usage.prompt_token_count,
+            output: usage.candidates_token_count + usage.thoughts_token_count,
+            cached: usage.cached_content_token_count,
+            reasoning: usage.thoughts_token_count,
+        }
+    }
+}
+
+fn finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => {
+            FinishReason::ContentFilter
+        }
+        "MALFORMED_FUNCTION_CALL" => FinishReason::ToolCalls,
+        other => FinishReason::Other(other.to_lowercase()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiPart {
+    // Optional because Gemini 3 might send a part with only a thoughtSignature
+    text: Option<String>,
+}
+
+impl GeminiResponse {
+    fn text(&self) -> String {
+        // Join ALL parts because Gemini 3 often splits thoughts and text
+        self.candidates
+            .first()
+            .and_then(|candidate| candidate.content.as_ref())
+            .map(|content| {
+                content
+                    .parts
+                    .iter()
+                    .filter_map(|part| part.text.as_deref())
+                    .collect::<String>()
+            })
+            .unwrap_or_default()
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.candidates
+            .first()
+            .and_then(|candidate| candidate.finish_reason.as_deref())
+            .map(finish_reason)
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage_metadata.as_ref().map(Usage::from)
+    }
+}
+
+impl LlmProvider for GoogleProvider {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn base_url(&self) -> &str {
+        "https://generativelanguage.googleapis.com/v1beta"
+    }
+
+    fn build_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        request: &ChatRequest,
+    ) -> Result<ProviderRequest> {
+        // Gemini exposes streaming as a separate method rather than a body flag
+        let url = if request.stream == Some(true) {
+            format!(
+                "{}/models/{}:streamGenerateContent?alt=sse&key={}",
+                base_url, request.model, api_key
+            )
+        } else {
+            format!(
+                "{}/models/{}:generateContent?key={}",
+                base_url, request.model, api_key
+            )
+        };
+
+        // Convert messages to Gemini format; the system prompt is a separate instruction
+        let (system, messages) = split_system(&request.messages);
+        let contents = messages
+            .iter()
+            .map(|msg| {
+                serde_json::json!({
+                    "parts": [{"text": msg.content}],
+                    "role": if msg.role == "assistant" { "model" } else { "user" }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut body = serde_json::json!({
+            "contents": contents,
+            "generationConfig": {
+                "temperature": request.temperature,
+                "maxOutputTokens": request.max_completion_tokens
+            }
+        });
+        if let Some(system) = system {
+            body["systemInstruction"] = serde_json::json!({ "parts": [{"text": system}] });
+        }
+
+        Ok(ProviderRequest {
+            url,
+            headers: HeaderMap::new(),
+            body,
+        })
+    }
+
+    fn parse_response(&self, body: &str) -> Result<ChatResponse> {
+        let response: GeminiResponse = parse_json(body)?;
+        Ok(ChatResponse {
+            text: response.text(),
+            usage: response.usage(),
+            finish_reason: response.finish_reason(),
+            id: response.response_id,
+            ..Default::default()
+        })
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Vec<StreamEvent>> {
+        let value = parse_stream_json(self.name(), data)?;
+        let response: GeminiResponse = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("JSON Decode Error: {}. \nRaw Event: {}", e, data))?;
+        let text = response.text();
+
+        // Every chunk carries the running usage totals; the last one the finish reason
+        let events = (!text.is_empty())
+            .then_some(StreamEvent::Text(text))
+            .into_iter()
+            .chain(response.usage().map(StreamEvent::Usage))
+            .chain(response.finish_reason().map(StreamEvent::Finish))
+            .collect();
+
+        Ok(events)
+    }
+}
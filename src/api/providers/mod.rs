@@ -0,0 +1,113 @@
+mod anthropic;
+mod google;
+mod openai;
+
+pub use anthropic::AnthropicProvider;
+pub use google::GoogleProvider;
+pub use openai::OpenAiProvider;
+
+use super::error::api_error;
+use super::models::{ChatRequest, ChatResponse, Message, StreamEvent};
+use anyhow::Result;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A fully built HTTP request for a provider's chat endpoint.
+pub struct ProviderRequest {
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: serde_json::Value,
+}
+
+/// Wire-format implementation for one LLM provider.
+///
+/// Implementations translate a provider-neutral `ChatRequest` into the
+/// provider's HTTP request and normalize its responses back into a
+/// `ChatResponse`, so `LlmClient` never has to know which API it talks to.
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Default API root, used unless the client is pointed elsewhere.
+    fn base_url(&self) -> &str;
+
+    fn build_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        request: &ChatRequest,
+    ) -> Result<ProviderRequest>;
+
+    fn parse_response(&self, body: &str) -> Result<ChatResponse>;
+
+    /// Extracts the text delta, usage and finish reason carried by one SSE
+    /// `data:` payload.
+    ///
+    /// Returns no events for frames that carry none of these (role headers,
+    /// pings, end-of-stream sentinels).
+    fn parse_stream_event(&self, data: &str) -> Result<Vec<StreamEvent>>;
+}
+
+/// Lookup table of the providers the client can talk to, keyed by name.
+pub struct ProviderRegistry {
+    providers: HashMap<String, Arc<dyn LlmProvider>>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self {
+            providers: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, provider: Arc<dyn LlmProvider>) {
+        self.providers.insert(provider.name().to_string(), provider);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn LlmProvider>> {
+        self.providers.get(name).cloned()
+    }
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(OpenAiProvider));
+        registry.register(Arc::new(AnthropicProvider));
+        registry.register(Arc::new(GoogleProvider));
+        registry
+    }
+}
+
+/// Separates system messages from the conversation, for APIs that take the
+/// system prompt in its own field. Several system messages are joined.
+fn split_system(messages: &[Message]) -> (Option<String>, Vec<&Message>) {
+    let (system, turns): (Vec<&Message>, Vec<&Message>) =
+        messages.iter().partition(|m| m.role == "system");
+
+    let system = (!system.is_empty()).then(|| {
+        system
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    });
+    (system, turns)
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(body: &str) -> Result<T> {
+    serde_json::from_str(body)
+        .map_err(|e| anyhow::anyhow!("JSON Decode Error: {}. \nRaw Body: {}", e, body))
+}
+
+/// Parses one stream event, turning an in-band error event into a typed error.
+fn parse_stream_json(provider: &str, data: &str) -> Result<serde_json::Value> {
+    let value: serde_json::Value = serde_json::from_str(data)
+        .map_err(|e| anyhow::anyhow!("JSON Decode Error: {}. \nRaw Event: {}", e, data))?;
+
+    if value.get("error").is_some() {
+        return Err(api_error(provider, None, data).into());
+    }
+
+    Ok(value)
+}
@@ -0,0 +1,151 @@
+use super::{parse_json, parse_stream_json, LlmProvider, ProviderRequest};
+use crate::api::models::{ChatRequest, ChatResponse, FinishReason, StreamEvent, Usage};
+use anyhow::Result;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+
+pub struct OpenAiProvider;
+
+#[derive(Debug, Deserialize)]
+struct OpenAiResponse {
+    id: String,
+    choices: Vec<Choice>,
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+    prompt_tokens_details: Option<PromptTokensDetails>,
+    completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionTokensDetails {
+    #[serde(default)]
+    reasoning_tokens: u64,
+}
+
+impl From<OpenAiUsage> for Usage {
+    fn from(usage: OpenAiUsage) -> Self {
+        Usage {
+            input: usage.prompt_tokens,
+            output: usage.completion_tokens,
+            cached: usage.prompt_tokens_details.map_or(0, |d| d.cached_tokens),
+            reasoning: usage
+                .completion_tokens_details
+                .map_or(0, |d| d.reasoning_tokens),
+        }
+    }
+}
+
+fn finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "function_call" => FinishReason::ToolCalls,
+        _ => FinishReason::from(reason.to_string()),
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn base_url(&self) -> &str {
+        "https://api.openai.com/v1"
+    }
+
+    fn build_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        request: &ChatRequest,
+    ) -> Result<ProviderRequest> {
+        let url = format!("{}/chat/completions", base_url);
+        let mut headers = HeaderMap::new();
+        // Local OpenAI-compatible servers usually run without a key
+        if !api_key.is_empty() {
+            headers.insert("Authorization", format!("Bearer {}", api_key).parse()?);
+        }
+
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": request.messages,
+            "max_completion_tokens": request.max_completion_tokens,
+            "temperature": request.temperature,
+            "stream": request.stream
+        });
+        // Streams only report usage, in a final frame, when asked to
+        if request.stream == Some(true) {
+            body["stream_options"] = serde_json::json!({"include_usage": true});
+        }
+
+        Ok(ProviderRequest { url, headers, body })
+    }
+
+    fn parse_response(&self, body: &str) -> Result<ChatResponse> {
+        let response: OpenAiResponse = parse_json(body)?;
+        let choice = response.choices.into_iter().next();
+        let finish_reason = choice
+            .as_ref()
+            .and_then(|choice| choice.finish_reason.as_deref())
+            .map(finish_reason);
+        let text = choice
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+
+        Ok(ChatResponse {
+            id: response.id,
+            text,
+            usage: response.usage.map(Usage::from),
+            finish_reason,
+            ..Default::default()
+        })
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Vec<StreamEvent>> {
+        if data == "[DONE]" {
+            return Ok(Vec::new());
+        }
+
+        let value = parse_stream_json(self.name(), data)?;
+        let choice = &value["choices"][0];
+        let mut events = Vec::new();
+
+        if let Some(text) = choice["delta"]["content"]
+            .as_str()
+            .filter(|t| !t.is_empty())
+        {
+            events.push(StreamEvent::Text(text.to_string()));
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            events.push(StreamEvent::Finish(finish_reason(reason)));
+        }
+        if value["usage"].is_object() {
+            let usage: OpenAiUsage = serde_json::from_value(value["usage"].clone())?;
+            events.push(StreamEvent::Usage(usage.into()));
+        }
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::config::manager::RetryConfig;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::error::Error as _;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Rate limits, timeouts and server-side failures are worth another try;
+/// other client errors (bad key, bad request) will fail the same way again.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+/// Whether a transport error is transient: a timeout, a refused connection,
+/// or a connection dropped mid-request.
+pub fn is_transient(error: &reqwest::Error) -> bool {
+    if error.is_timeout() || error.is_connect() {
+        return true;
+    }
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = cause.source();
+    }
+    false
+}
+
+/// How long the server asked us to wait, from `Retry-After` or, failing
+/// that, the reset time of an exhausted OpenAI or Anthropic rate limit.
+pub fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(value) = header("retry-after-ms") {
+        return value.trim().parse().ok().map(Duration::from_millis);
+    }
+    if let Some(value) = header("retry-after") {
+        return parse_retry_after(value);
+    }
+
+    ["requests", "tokens", "input-tokens", "output-tokens"]
+        .iter()
+        .filter_map(|limit| {
+            let openai = (
+                header(&format!("x-ratelimit-remaining-{}", limit)),
+                header(&format!("x-ratelimit-reset-{}", limit)).and_then(parse_go_duration),
+            );
+            let anthropic = (
+                header(&format!("anthropic-ratelimit-{}-remaining", limit)),
+                header(&format!("anthropic-ratelimit-{}-reset", limit)).and_then(until_timestamp),
+            );
+            [openai, anthropic]
+                .into_iter()
+                .find_map(|(remaining, reset)| (remaining?.trim() == "0").then_some(reset?))
+        })
+        .max()
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// Anthropic reports limit resets as RFC 3339 timestamps.
+fn until_timestamp(value: &str) -> Option<Duration> {
+    let reset = chrono::DateTime::parse_from_rfc3339(value.trim()).ok()?;
+    (reset.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// OpenAI reports limit resets as Go durations such as `1s`, `6m0s` or `20ms`.
+fn parse_go_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+        total += number * scale;
+    }
+
+    Duration::try_from_secs_f64(total).ok()
+}
+
+/// The delay before retry number `retry` (1-based): the server's request if it
+/// made one, otherwise exponential backoff; capped at `max_delay_ms`, with
+/// part of it randomized.
+pub fn retry_delay(config: &RetryConfig, retry: u32, server_delay: Option<Duration>) -> Duration {
+    let max = Duration::from_millis(config.max_delay_ms);
+    if let Some(delay) = server_delay {
+        return delay.min(max);
+    }
+
+    let backoff = Duration::from_millis(config.initial_delay_ms)
+        .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+        .min(max);
+    let jitter = config.jitter.clamp(0.0, 1.0);
+    backoff.mul_f64(1.0 - jitter * random_fraction())
+}
+
+/// A number in `[0, 1)`, random enough to spread out retries.
+fn random_fraction() -> f64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_delay_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining-requests", "5".parse().unwrap());
+        headers.insert("x-ratelimit-reset-requests", "1s".parse().unwrap());
+        headers.insert("x-ratelimit-remaining-tokens", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset-tokens", "1m30.5s".parse().unwrap());
+        assert_eq!(server_delay(&headers), Some(Duration::from_millis(90_500)));
+
+        headers.insert("retry-after", "2".parse().unwrap());
+        assert_eq!(server_delay(&headers), Some(Duration::from_secs(2)));
+
+        assert_eq!(parse_go_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_go_duration("soon"), None);
+    }
+
+    #[test]
+    fn test_retry_delay_backoff_and_cap() {
+        let config = RetryConfig {
+            max_attempts: 5,
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+            jitter: 0.0,
+        };
+        assert_eq!(retry_delay(&config, 1, None), Duration::from_millis(100));
+        assert_eq!(retry_delay(&config, 3, None), Duration::from_millis(400));
+        assert_eq!(retry_delay(&config, 10, None), Duration::from_millis(1_000));
+        assert_eq!(
+            retry_delay(&config, 1, Some(Duration::from_secs(60))),
+            Duration::from_millis(1_000)
+        );
+
+        let jittered = retry_delay(
+            &RetryConfig {
+                jitter: 0.5,
+                ..config
+            },
+            2,
+            None,
+        );
+        assert!(jittered > Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+    }
+}
@@ -0,0 +1,110 @@
+/// A single Server-Sent Event as dispatched by the decoder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental decoder for `text/event-stream` bodies.
+///
+/// Bytes can be fed in arbitrary chunks (they rarely line up with event
+/// boundaries on the wire); complete events are returned as soon as their
+/// terminating blank line has been seen.
+#[derive(Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        // Lines starting with a colon are comments (often used as keep-alives)
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = std::mem::take(&mut self.data).join("\n");
+        Some(SseEvent { event, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut decoder = SseDecoder::new();
+
+        assert!(decoder.feed(b"event: content_block_delta\nda").is_empty());
+        let events = decoder.feed(b"ta: {\"a\":1}\r\n\r\n: ping\n\ndata: two\n\n");
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("content_block_delta".to_string()),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "two".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiline_data_is_joined() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.feed(b"data: first\ndata: second\n\n");
+
+        assert_eq!(events[0].data, "first\nsecond");
+    }
+}
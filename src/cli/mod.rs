@@ -1,4 +1,9 @@
-use clap::{Parser, Subcommand};
+use crate::output::OutputFormat;
+use crate::session::SessionFormat;
+use crate::usage::GroupBy;
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "llm-cli")]
@@ -13,27 +18,7 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Ask a one-shot question to the LLM
-    Ask {
-        /// The question to ask
-        #[arg(required_unless_present = "file")]
-        query: Option<String>,
-
-        /// Read query from file
-        #[arg(short, long)]
-        file: Option<String>,
-
-        /// Output file path
-        #[arg(short, long)]
-        output: Option<String>,
-
-        /// Model to use (overrides config)
-        #[arg(short, long)]
-        model: Option<String>,
-
-        /// Use a template
-        #[arg(short, long)]
-        template: Option<String>,
-    },
+    Ask(AskArgs),
 
     /// Start an interactive chat session
     Chat {
@@ -44,6 +29,10 @@ pub enum Commands {
         /// Model to use (overrides config)
         #[arg(short, long)]
         model: Option<String>,
+
+        /// System prompt (overrides the model's and chat.system)
+        #[arg(long)]
+        system: Option<String>,
     },
 
     /// Manage configuration
@@ -74,6 +63,66 @@ pub enum Commands {
         )]
         models: Vec<String>,
     },
+
+    /// Report token usage and spend recorded by ask, chat and compare
+    Usage {
+        /// Group totals by day, model, provider or session
+        #[arg(long, value_enum, default_value_t = GroupBy::Day)]
+        by: GroupBy,
+
+        /// Only count requests made on or after this day (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+
+        /// Only count requests made on or before this day (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+    },
+}
+
+#[derive(Args)]
+pub struct AskArgs {
+    /// The question to ask (available to templates as {{query}})
+    #[arg(required_unless_present_any = ["file", "template"])]
+    pub query: Option<String>,
+
+    /// Read query from file
+    #[arg(short, long)]
+    pub file: Option<String>,
+
+    /// Write the response to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Model to use (overrides config)
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// Use a template
+    #[arg(short, long)]
+    pub template: Option<String>,
+
+    /// System prompt (overrides the template's, the model's and chat.system)
+    #[arg(long)]
+    pub system: Option<String>,
+
+    /// Continue a stored session: send its recent turns along and append
+    /// this exchange to it (created if missing)
+    #[arg(long)]
+    pub session: Option<String>,
+
+    /// Template variable as key=value; use @path to inline a file, - for stdin.
+    /// Repeat a key to build a list for {{#each}}
+    #[arg(long = "var", value_name = "KEY=VALUE", requires = "template")]
+    pub vars: Vec<String>,
+
+    /// Print token usage and the finish reason after the response (to stderr)
+    #[arg(long)]
+    pub usage: bool,
 }
 
 #[derive(Subcommand)]
@@ -101,11 +150,80 @@ pub enum ConfigAction {
 #[derive(Subcommand)]
 pub enum SessionAction {
     /// List all sessions
-    List,
+    List {
+        /// Only list sessions with this tag (repeat to require several)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
     /// Show session details
     Show {
         /// Session name
         name: String,
+        /// Draw every branch of the conversation, with message ids
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Search message content across all sessions
+    Search {
+        /// Words to find (all must appear), or a pattern with --regex
+        query: String,
+        /// Treat the query as a regular expression
+        #[arg(long)]
+        regex: bool,
+        /// Only search messages with this role (user, assistant, system)
+        #[arg(long)]
+        role: Option<String>,
+        /// Only search replies written by this model
+        #[arg(long)]
+        model: Option<String>,
+        /// Only search messages sent on or after this day (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Only search messages sent on or before this day (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+    },
+    /// Copy a conversation up to a message into a new session
+    Fork {
+        /// Session name
+        name: String,
+        /// Id of the last message to keep (see `session show --tree`)
+        #[arg(long)]
+        at: usize,
+        /// Name of the new session
+        new_name: String,
+    },
+    /// Reword an earlier user message, starting a new branch from it
+    Edit {
+        /// Session name
+        name: String,
+        /// Id of the user message to replace (see `session show --tree`)
+        #[arg(long)]
+        at: usize,
+        /// The new message
+        content: String,
+    },
+    /// Add tags to a session, or remove them
+    Tag {
+        /// Session name
+        name: String,
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+        /// Remove the tags instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Set a session's description or title
+    Describe {
+        /// Session name
+        name: String,
+        /// What the session is about
+        #[arg(required_unless_present = "title")]
+        description: Option<String>,
+        /// Replace the title taken from the first question
+        #[arg(long)]
+        title: Option<String>,
     },
     /// Delete a session
     Delete {
@@ -119,15 +237,30 @@ pub enum SessionAction {
         /// Output file path
         #[arg(short, long)]
         output: String,
+        /// File format; only json keeps branches off the active one
+        #[arg(long, value_enum, default_value_t = SessionFormat::Json)]
+        format: SessionFormat,
+    },
+    /// Import sessions from an export, an OpenAI messages file, or a ChatGPT
+    /// or Claude data export
+    Import {
+        /// File to import (.json, .jsonl, .md, .html or a .zip archive)
+        file: PathBuf,
+        /// Session name; numbered when the file holds several conversations
+        #[arg(long)]
+        name: Option<String>,
     },
 }
 
 #[derive(Subcommand)]
 pub enum TemplateAction {
-    /// Create a new template
+    /// Create or edit a template (opens $EDITOR, or reads piped stdin)
     Create {
         /// Template name
         name: String,
+        /// Store in the user-wide template directory instead of ./templates
+        #[arg(short, long)]
+        global: bool,
     },
     /// List all templates
     List,
@@ -140,5 +273,8 @@ pub enum TemplateAction {
     Delete {
         /// Template name
         name: String,
+        /// Delete the user-wide copy rather than the one ./templates resolves to
+        #[arg(short, long)]
+        global: bool,
     },
 }
@@ -1,74 +1,374 @@
-use crate::api::{ChatRequest, LlmClient, Message};
+use crate::api::{ChatRequest, ChatResponse, LlmClient, Message, StreamEvent};
+use crate::cli::AskArgs;
+use crate::config::manager::ModelInfo;
 use crate::config::ConfigManager;
-use crate::output::OutputFormatter;
+use crate::output::{OutputFormat, OutputFormatter, ResponseEnvelope};
+use crate::session::{recent_turns, SessionMessage, SessionStore};
+use crate::template::{FrontMatter, TemplateEngine, TemplateStore, Value};
+use crate::usage::{enforce_budget, open_ledger, record_usage, PlannedCost, UsageLedger};
+use crate::utils::LlmCliError;
 use anyhow::Context;
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::io::Read;
+use std::time::Instant;
 /// Executes the 'ask' command to get a one-shot response from the LLM.
-pub async fn execute(
-    query: Option<String>,
-    file: Option<String>,
-    _output: Option<String>,
-    model: Option<String>,
-    _template: Option<String>,
-) -> Result<()> {
+pub async fn execute(args: AskArgs) -> Result<()> {
+    let AskArgs {
+        query,
+        file,
+        output,
+        format,
+        model,
+        template,
+        system,
+        session,
+        vars,
+        usage,
+    } = args;
+
     // 1. Initialize Configuration
     let config_mgr = ConfigManager::new()?;
     let config = config_mgr.get();
 
-    // 2. Determine the model to use
-    let model_name = model.unwrap_or_else(|| config.models.default.clone());
-    let model_info = config_mgr
-        .get_model_info(&model_name)
-        .context(format!("Model '{}' not found in config.toml", model_name))?;
-    // 3. Get API key for the provider
-    let api_key = config_mgr.get_api_key(&model_info.provider)?;
-
-    // 4. Resolve the Query Text
+    // 2. Resolve the Query Text
     let query_text = if let Some(q) = query {
-        q
+        Some(q)
     } else if let Some(f) = file {
-        std::fs::read_to_string(&f).map_err(|e| anyhow!("Failed to read file {}: {}", f, e))?
+        Some(std::fs::read_to_string(&f).map_err(|e| anyhow!("Failed to read file {}: {}", f, e))?)
     } else {
-        bail!("Either a query string or a --file path must be provided.");
+        None
+    };
+
+    let (query_text, front_matter) = match (template, query_text) {
+        (Some(name), query_text) => render_template(&name, vars, query_text)?,
+        (None, Some(q)) => (q, FrontMatter::default()),
+        (None, None) => {
+            return Err(LlmCliError::InvalidInput(
+                "Either a query string, a --file path or a --template must be provided."
+                    .to_string(),
+            )
+            .into())
+        }
     };
 
-    // 5. Initialize Client and Formatter
-    let client = LlmClient::new(api_key, &model_info.provider);
+    // 3. Determine the model to use: --model, then the template's choice, then the default
+    let model_name = model
+        .or_else(|| front_matter.model.clone())
+        .unwrap_or_else(|| config.models.default.clone());
+    // A fallback alias resolves to several models; the first is tried first
+    let models = config_mgr.resolve_models(&model_name)?;
+    let model_info = models[0];
+
+    // 4. Initialize Client and Formatter
+    let client = LlmClient::for_models(&config_mgr, &models)?;
     let formatter = OutputFormatter::new(
         config.output.syntax_highlighting,
         config.output.markdown_rendering,
     );
 
-    // 6. Build the Request
+    // 5. Build the Request: --system and template settings, then the model's
+    // defaults, then ChatConfig
+    let mut messages = Vec::new();
+    let system = system
+        .as_deref()
+        .or(front_matter.system.as_deref())
+        .or(model_info.system_or(&config.chat));
+    if let Some(system) = system {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: system.to_string(),
+        });
+    }
+    let session = session.map(AskSession::open).transpose()?;
+    let session_name = session.as_ref().map(|s| s.name.as_str());
+    if let Some(session) = &session {
+        messages.extend(session.context(config.session.max_history));
+    }
+    messages.push(Message {
+        role: "user".to_string(),
+        content: query_text.clone(),
+    });
+
     let request = ChatRequest {
-        model: model_name.clone(),
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: query_text,
-        }],
-        temperature: Some(config.chat.temperature),
-        max_completion_tokens: config.chat.max_tokens,
-        stream: Some(false),
+        model: model_info.name.clone(),
+        messages,
+        temperature: Some(
+            front_matter
+                .temperature
+                .unwrap_or_else(|| model_info.temperature_or(&config.chat)),
+        ),
+        max_completion_tokens: front_matter
+            .max_tokens
+            .unwrap_or_else(|| model_info.max_tokens_or(&config.chat)),
+        stream: Some(config.chat.streaming),
     };
 
-    formatter.print_info(&format!(
-        "🚀 Using provider: {} with model: {}",
-        model_info.provider, model_name
-    ));
+    // Refuse before spending anything if the request could break a budget
+    let ledger = open_ledger();
+    enforce_budget(
+        &config.budget,
+        ledger.as_ref(),
+        &[PlannedCost::new(
+            models.iter().copied(),
+            &request.messages,
+            request.max_completion_tokens,
+        )],
+    )?;
+
+    // Decorations would corrupt machine-readable output on stdout
+    if format == OutputFormat::Text {
+        formatter.print_info(&format!(
+            "🚀 Using provider: {} with model: {}",
+            model_info.provider, model_name
+        ));
+    }
 
     // 6. Perform the API Call
-    match client.chat(request).await {
-        Ok(response) => {
-            let text = response.get_text(); // This uses your new logic from model.rs
-            if text.is_empty() {
-                println!("(Received empty response from model)");
-            } else {
-                println!("{}", text); // <-- THIS is what's missing
-            }
+    let start = Instant::now();
+    if config.chat.streaming && format == OutputFormat::Text && output.is_none() {
+        let response = stream_response(&client, &formatter, request).await?;
+        if let Some(session) = &session {
+            let reply = SessionMessage::reply(
+                &response,
+                &answered_by(&models, &response).provider,
+                start.elapsed().as_millis() as u64,
+            );
+            session.append(&query_text, reply)?;
         }
-        Err(e) => {
-            eprintln!("Error: {}", e);
+        report(
+            &formatter,
+            ledger.as_ref(),
+            &models,
+            &response,
+            session_name,
+            usage,
+        );
+        return Ok(());
+    }
+
+    let response = client.chat(request).await?;
+    let answered = answered_by(&models, &response);
+
+    let envelope = ResponseEnvelope {
+        id: response.id.clone(),
+        model: answered.name.clone(),
+        provider: answered.provider.clone(),
+        text: response.get_text(),
+        latency_ms: start.elapsed().as_millis(),
+        usage: response.usage,
+        finish_reason: response.finish_reason.clone(),
+    };
+    let rendered = envelope.render(format, &query_text)?;
+
+    // 7. Deliver the Response
+    match output {
+        Some(path) => {
+            std::fs::write(&path, format!("{}\n", rendered.trim_end_matches('\n')))
+                .map_err(|e| anyhow!("Failed to write output file {}: {}", path, e))?;
+            if format == OutputFormat::Text {
+                formatter.print_success(&format!("Saved response to {}", path));
+            }
         }
+        None if format == OutputFormat::Text => formatter.print_response(&rendered),
+        None if format == OutputFormat::Raw => print!("{}", rendered),
+        None => println!("{}", rendered.trim_end_matches('\n')),
+    }
+    if let Some(session) = &session {
+        let reply =
+            SessionMessage::reply(&response, &answered.provider, envelope.latency_ms as u64);
+        session.append(&query_text, reply)?;
     }
+    report(
+        &formatter,
+        ledger.as_ref(),
+        &models,
+        &response,
+        session_name,
+        usage,
+    );
+
     Ok(())
 }
+
+/// A stored session that `ask --session` continues.
+struct AskSession {
+    store: SessionStore,
+    name: String,
+    history: Vec<SessionMessage>,
+}
+
+impl AskSession {
+    fn open(name: String) -> Result<Self> {
+        let store = SessionStore::new()?;
+        let history = store
+            .load_session(&name)?
+            .map(|s| s.messages())
+            .unwrap_or_default();
+        Ok(Self {
+            store,
+            name,
+            history,
+        })
+    }
+
+    /// The most recent turns, as many as `session.max_history` allows.
+    fn context(&self, max_history: usize) -> impl Iterator<Item = Message> + '_ {
+        recent_turns(&self.history, max_history)
+            .iter()
+            .map(|m| Message {
+                role: m.role.clone(),
+                content: m.content.clone(),
+            })
+    }
+
+    /// Appends the question and its answer to the stored session.
+    fn append(&self, question: &str, reply: SessionMessage) -> Result<()> {
+        let exchange = [SessionMessage::new("user", question.to_string()), reply];
+        for message in exchange {
+            self.store.add_message(&self.name, message)?;
+        }
+        Ok(())
+    }
+}
+
+/// The model of a fallback chain that produced the response.
+fn answered_by<'a>(models: &[&'a ModelInfo], response: &ChatResponse) -> &'a ModelInfo {
+    models
+        .iter()
+        .find(|model| model.name == response.model)
+        .copied()
+        .unwrap_or(models[0])
+}
+
+/// Records the response's usage in the ledger against the model that
+/// answered, then reports fallbacks, truncation and, when asked for, token
+/// usage on stderr.
+fn report(
+    formatter: &OutputFormatter,
+    ledger: Option<&UsageLedger>,
+    models: &[&ModelInfo],
+    response: &ChatResponse,
+    session: Option<&str>,
+    usage: bool,
+) {
+    record_usage(
+        ledger,
+        "ask",
+        answered_by(models, response),
+        response.usage,
+        session,
+    );
+    formatter.note_fallback(&models[0].name, response);
+    formatter.warn_if_truncated(response);
+    if usage {
+        formatter.print_usage(response);
+    }
+}
+
+/// Prints tokens to stdout as they arrive from the provider, returning the
+/// assembled response.
+async fn stream_response(
+    client: &LlmClient,
+    formatter: &OutputFormatter,
+    request: ChatRequest,
+) -> Result<ChatResponse> {
+    let mut stream = client.chat_stream(request).await?;
+
+    let mut printer = formatter.stream();
+    let mut response = ChatResponse::default();
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(event) => {
+                if let StreamEvent::Text(text) = &event {
+                    printer.write(text)?;
+                }
+                response.apply(event);
+            }
+            Err(e) => {
+                println!();
+                return Err(e);
+            }
+        }
+    }
+
+    printer.finish();
+    Ok(response)
+}
+
+/// Loads a stored template and renders it with the `--var` bindings,
+/// returning the prompt together with the template's front matter.
+///
+/// The query (positional argument or `--file`), if any, is bound as `{{query}}`
+/// unless a `--var query=...` overrides it.
+fn render_template(
+    name: &str,
+    vars: Vec<String>,
+    query: Option<String>,
+) -> Result<(String, FrontMatter)> {
+    let store = TemplateStore::new()?;
+    let content = store
+        .load(name)?
+        .context(format!("Template '{}' not found", name))?;
+    let (front_matter, body) = FrontMatter::split(&content)?;
+
+    let mut variables = HashMap::new();
+    if let Some(query) = query {
+        variables.insert("query".to_string(), Value::Text(query));
+    }
+    variables.extend(parse_vars(vars)?);
+    front_matter.apply(&mut variables)?;
+
+    let mut engine = TemplateEngine::new().with_store(store);
+    engine.add_template(name.to_string(), body.to_string());
+    let prompt = engine.render(name, &variables)?;
+
+    Ok((prompt, front_matter))
+}
+
+/// Parses `key=value` pairs, inlining files for `@path` values and stdin for `-`.
+///
+/// Repeating a key collects its values into a list for `{{#each}}`.
+fn parse_vars(vars: Vec<String>) -> Result<HashMap<String, Value>> {
+    let mut variables: HashMap<String, Value> = HashMap::new();
+    let mut stdin_used = false;
+
+    for var in vars {
+        let (key, value) = var.split_once('=').ok_or_else(|| {
+            LlmCliError::InvalidInput(format!("Invalid --var '{}': expected key=value", var))
+        })?;
+
+        let value = if value == "-" {
+            if stdin_used {
+                return Err(LlmCliError::InvalidInput(
+                    "Only one --var can read from stdin".to_string(),
+                )
+                .into());
+            }
+            stdin_used = true;
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .context(format!("Failed to read --var {} from stdin", key))?;
+            content
+        } else if let Some(path) = value.strip_prefix('@') {
+            std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read file {} for --var {}: {}", path, key, e))?
+        } else {
+            value.to_string()
+        };
+
+        let merged = match variables.remove(key) {
+            None => Value::Text(value),
+            Some(Value::Text(first)) => Value::List(vec![first, value]),
+            Some(Value::List(mut items)) => {
+                items.push(value);
+                Value::List(items)
+            }
+        };
+        variables.insert(key.to_string(), merged);
+    }
+
+    Ok(variables)
+}
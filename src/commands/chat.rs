@@ -1,16 +1,572 @@
-use anyhow::Result;
+use crate::api::{ChatRequest, ChatResponse, LlmClient, Message, StreamEvent};
+use crate::config::manager::ModelInfo;
+use crate::config::ConfigManager;
+use crate::output::OutputFormatter;
+use crate::session::{recent_turns, Session, SessionMessage, SessionStore};
+use crate::usage::{
+    enforce_budget, estimate_tokens, open_ledger, record_usage, PlannedCost, UsageLedger,
+};
+use crate::utils::LlmCliError;
+use anyhow::{Context, Result};
 use colored::*;
+use directories::ProjectDirs;
+use futures::StreamExt;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+const HELP: &str = "\
+/model [name]        Show or switch the model (and adopt its defaults)
+/system [text|clear] Show, set or clear the system prompt
+/temperature [value] Show or set the sampling temperature
+/save [name]         Save the conversation (optionally under a new session name)
+/load <name>         Load another session
+/clear               Start over with an empty conversation
+/undo                Remove the last exchange
+/retry               Regenerate the last reply
+/copy                Copy the last reply to the clipboard
+/help                Show this help
+exit, quit           Leave the chat";
+
+/// What the REPL loop should do after a slash command.
+enum Flow {
+    Continue,
+    /// Regenerate the last answer; carries the reply being replaced, if any.
+    Retry(Option<SessionMessage>),
+}
+
+/// Live state of an interactive chat: the conversation so far and where it is persisted.
+struct ChatState {
+    config_mgr: ConfigManager,
+    store: SessionStore,
+    session_name: String,
+    /// The model or fallback alias the user chose
+    model_name: String,
+    /// The models tried for `model_name`, in order
+    chain: Vec<ModelInfo>,
+    client: LlmClient,
+    system: Option<String>,
+    temperature: f32,
+    history: Vec<SessionMessage>,
+    formatter: OutputFormatter,
+    ledger: Option<UsageLedger>,
+}
+
+impl ChatState {
+    fn new(session: Option<String>, model: Option<String>, system: Option<String>) -> Result<Self> {
+        let config_mgr = ConfigManager::new()?;
+        let store = SessionStore::new()?;
+
+        let model_name = model.unwrap_or_else(|| config_mgr.get().models.default.clone());
+        let (chain, client) = Self::connect(&config_mgr, &model_name)?;
+        let model = &chain[0];
+        let temperature = model.temperature_or(&config_mgr.get().chat);
+        let system = system.or_else(|| model.system_or(&config_mgr.get().chat).map(str::to_string));
+        let formatter = OutputFormatter::new(
+            config_mgr.get().output.syntax_highlighting,
+            config_mgr.get().output.markdown_rendering,
+        );
+
+        let session_name = session
+            .unwrap_or_else(|| format!("chat-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        let history = store
+            .load_session(&session_name)?
+            .map(|s| s.messages())
+            .unwrap_or_default();
+
+        Ok(Self {
+            config_mgr,
+            store,
+            session_name,
+            model_name,
+            chain,
+            client,
+            system,
+            temperature,
+            history,
+            formatter,
+            ledger: open_ledger(),
+        })
+    }
+
+    fn connect(
+        config_mgr: &ConfigManager,
+        model_name: &str,
+    ) -> Result<(Vec<ModelInfo>, LlmClient)> {
+        let models = config_mgr.resolve_models(model_name)?;
+        let client = LlmClient::for_models(config_mgr, &models)?;
+        Ok((models.into_iter().cloned().collect(), client))
+    }
+
+    /// The model tried first for `model_name`.
+    fn model(&self) -> &ModelInfo {
+        &self.chain[0]
+    }
+
+    /// The most recent messages that fit in `session.max_history` and, if the
+    /// model declares one, its context window with room left for the reply.
+    ///
+    /// Only the context sent to the model is trimmed; the stored session keeps
+    /// the full transcript.
+    fn context(&self) -> Vec<Message> {
+        let config = self.config_mgr.get();
+
+        let system = self.system.as_ref().map(|prompt| Message {
+            role: "system".to_string(),
+            content: prompt.clone(),
+        });
+        let turns: Vec<Message> = recent_turns(&self.history, config.session.max_history)
+            .iter()
+            .map(|m| Message {
+                role: m.role.clone(),
+                content: m.content.clone(),
+            })
+            .collect();
+
+        let mut context: Vec<Message> = system.into_iter().chain(turns).collect();
+        if let Some(window) = self.model().context_window {
+            let budget =
+                u64::from(window).saturating_sub(self.model().max_tokens_or(&config.chat).into());
+            fit_window(&mut context, usize::from(self.system.is_some()), budget);
+        }
+        context
+    }
+
+    fn push(&mut self, role: &str, content: String) {
+        self.history.push(SessionMessage::new(role, content));
+    }
+
+    fn persist(&self, message: &SessionMessage) -> Result<()> {
+        if !self.config_mgr.get().session.auto_save {
+            return Ok(());
+        }
+
+        self.store.add_message(&self.session_name, message.clone())
+    }
+
+    /// Makes the live conversation the stored session's active branch; any
+    /// messages it no longer has stay on their own branch.
+    fn save(&self) -> Result<()> {
+        let mut session = self
+            .store
+            .load_session(&self.session_name)?
+            .unwrap_or_else(|| Session::new(&self.session_name));
+
+        session.set_messages(self.history.clone());
+        session.updated_at = chrono::Utc::now().timestamp();
+        self.store.save_session(&session)
+    }
+
+    /// Re-syncs the stored session after the history was rewritten rather than appended to.
+    fn sync(&self) -> Result<()> {
+        if self.config_mgr.get().session.auto_save {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Sends the conversation and prints the reply, returning `None` if the
+    /// user cancelled with Ctrl-C before it completed.
+    async fn send(&self) -> Result<Option<SessionMessage>> {
+        let config = self.config_mgr.get();
+        let request = ChatRequest {
+            model: self.model().name.clone(),
+            messages: self.context(),
+            max_completion_tokens: self.model().max_tokens_or(&config.chat),
+            temperature: Some(self.temperature),
+            stream: Some(config.chat.streaming),
+        };
+
+        enforce_budget(
+            &config.budget,
+            self.ledger.as_ref(),
+            &[PlannedCost::new(
+                &self.chain,
+                &request.messages,
+                request.max_completion_tokens,
+            )],
+        )?;
+
+        let start = Instant::now();
+        let reply = tokio::select! {
+            reply = self.receive(request, config.chat.streaming) => reply?,
+            _ = tokio::signal::ctrl_c() => return Ok(None),
+        };
+
+        // Bill the model that answered, which a fallback chain may have changed
+        let answered = self
+            .config_mgr
+            .get_model_info(&reply.model)
+            .unwrap_or(self.model());
+        record_usage(
+            self.ledger.as_ref(),
+            "chat",
+            answered,
+            reply.usage,
+            Some(&self.session_name),
+        );
+        let latency_ms = start.elapsed().as_millis() as u64;
+        Ok(Some(SessionMessage::reply(
+            &reply,
+            &answered.provider,
+            latency_ms,
+        )))
+    }
+
+    async fn receive(&self, request: ChatRequest, streaming: bool) -> Result<ChatResponse> {
+        if !streaming {
+            let response = self.client.chat(request).await?;
+            self.formatter.print_response(&response.text);
+            self.formatter.note_fallback(&self.model().name, &response);
+            self.formatter.warn_if_truncated(&response);
+            return Ok(response);
+        }
+
+        let mut stream = self.client.chat_stream(request).await?;
+        let mut printer = self.formatter.stream();
+        let mut response = ChatResponse::default();
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            if let StreamEvent::Text(token) = &event {
+                printer.write(token)?;
+            }
+            response.apply(event);
+        }
+        printer.finish();
+        self.formatter.note_fallback(&self.model().name, &response);
+        self.formatter.warn_if_truncated(&response);
+
+        Ok(response)
+    }
+
+    async fn turn(&mut self, input: String) -> Result<()> {
+        self.push("user", input);
+
+        let reply = match self.answer().await {
+            Ok(Some(reply)) => reply,
+            outcome => {
+                // On cancellation or failure, drop the unanswered question again
+                self.history.pop();
+                return outcome.map(|_| ());
+            }
+        };
+
+        self.history.push(reply);
+        let exchange = &self.history[self.history.len() - 2..];
+        for message in exchange {
+            self.persist(message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Regenerates the answer to the trailing user message, restoring the
+    /// replaced reply if the new one does not complete.
+    async fn retry(&mut self, previous: Option<SessionMessage>) -> Result<()> {
+        let reply = match self.answer().await {
+            Ok(Some(reply)) => reply,
+            outcome => {
+                self.history.extend(previous);
+                return outcome.map(|_| ());
+            }
+        };
+
+        self.history.push(reply);
+        self.sync()
+    }
+
+    /// Sends the conversation, reporting a Ctrl-C cancellation as `None`.
+    async fn answer(&self) -> Result<Option<SessionMessage>> {
+        let reply = self.send().await?;
+        if reply.is_none() {
+            println!("\n{}", "Request cancelled".yellow());
+        }
+        Ok(reply)
+    }
+
+    fn last_reply(&self) -> Option<&SessionMessage> {
+        self.history.iter().rev().find(|m| m.role == "assistant")
+    }
+
+    fn handle_command(&mut self, line: &str) -> Result<Flow> {
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+
+        match command {
+            "/help" => println!("{}", HELP),
+            "/model" if arg.is_empty() => {
+                println!(
+                    "Current model: {} ({})",
+                    self.model_name.cyan(),
+                    self.model().provider
+                );
+                for model in self.config_mgr.get_available_models() {
+                    println!("  {} - {}", model.name.cyan(), model.display_name);
+                }
+            }
+            "/model" => {
+                let (chain, client) = Self::connect(&self.config_mgr, arg)?;
+                let model = &chain[0];
+                // The new model's own defaults replace the current settings
+                if let Some(temperature) = model.temperature {
+                    self.temperature = temperature;
+                }
+                if model.system.is_some() {
+                    self.system = model.system.clone();
+                }
+                self.model_name = arg.to_string();
+                self.chain = chain;
+                self.client = client;
+                println!("{} Switched to {}", "✓".green(), arg.cyan());
+            }
+            "/system" if arg.is_empty() => match &self.system {
+                Some(prompt) => println!("System prompt: {}", prompt),
+                None => println!("No system prompt set"),
+            },
+            "/system" if arg == "clear" => {
+                self.system = None;
+                println!("{} System prompt cleared", "✓".green());
+            }
+            "/system" => {
+                self.system = Some(arg.to_string());
+                println!("{} System prompt set", "✓".green());
+            }
+            "/temperature" if arg.is_empty() => println!("Temperature: {}", self.temperature),
+            "/temperature" => {
+                self.temperature = arg.parse().map_err(|_| {
+                    LlmCliError::InvalidInput(format!("Invalid temperature: {}", arg))
+                })?;
+                println!("{} Temperature set to {}", "✓".green(), self.temperature);
+            }
+            "/save" => {
+                if !arg.is_empty() {
+                    self.session_name = arg.to_string();
+                }
+                self.save()?;
+                println!(
+                    "{} Saved session '{}'",
+                    "✓".green(),
+                    self.session_name.cyan()
+                );
+            }
+            "/load" if arg.is_empty() => anyhow::bail!("Usage: /load <session>"),
+            "/load" => {
+                let session = self.store.load_session(arg)?.ok_or_else(|| {
+                    LlmCliError::SessionError(format!("Session '{}' not found", arg))
+                })?;
+                self.history = session.messages();
+                self.session_name = session.name;
+                println!(
+                    "{} Loaded session '{}' ({} messages)",
+                    "✓".green(),
+                    self.session_name.cyan(),
+                    self.history.len()
+                );
+            }
+            "/clear" => {
+                self.history.clear();
+                self.sync()?;
+                println!("{} Conversation cleared", "✓".green());
+            }
+            "/undo" => {
+                if self.history.last().is_some_and(|m| m.role == "assistant") {
+                    self.history.pop();
+                }
+                match self.history.pop() {
+                    Some(_) => {
+                        self.sync()?;
+                        println!("{} Removed the last exchange", "✓".green());
+                    }
+                    None => println!("{}", "Nothing to undo".yellow()),
+                }
+            }
+            "/retry" => {
+                let previous = if self.history.last().is_some_and(|m| m.role == "assistant") {
+                    self.history.pop()
+                } else {
+                    None
+                };
+                if self.history.last().is_none_or(|m| m.role != "user") {
+                    self.history.extend(previous);
+                    anyhow::bail!("Nothing to retry");
+                }
+                return Ok(Flow::Retry(previous));
+            }
+            "/copy" => {
+                let reply = self.last_reply().context("No reply to copy yet")?;
+                copy_to_clipboard(&reply.content)?;
+                println!("{} Copied the last reply", "✓".green());
+            }
+            _ => anyhow::bail!("Unknown command: {} (try /help)", command),
+        }
+
+        Ok(Flow::Continue)
+    }
+}
+
+/// Drops the oldest exchanges after the first `keep` messages until `context`
+/// fits in `budget` tokens or only the last question is left. A question goes
+/// together with its reply, so the context still opens with a question.
+fn fit_window(context: &mut Vec<Message>, keep: usize, budget: u64) {
+    while context.len() > keep + 1 && estimate_tokens(context) > budget {
+        let Some(next) = context[keep + 1..].iter().position(|m| m.role == "user") else {
+            break;
+        };
+        context.drain(keep..=keep + next);
+    }
+}
+
+/// Pipes text into the first clipboard utility available on this platform.
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let candidates: [(&str, &[&str]); 5] = [
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        ("clip.exe", &[]),
+    ];
+
+    for (program, args) in candidates {
+        let Ok(mut child) = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn()
+        else {
+            continue;
+        };
+
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+
+    anyhow::bail!("No clipboard utility found (tried pbcopy, wl-copy, xclip, xsel, clip.exe)")
+}
+
+fn history_path() -> Option<PathBuf> {
+    let proj_dirs = ProjectDirs::from("com", "llm-cli", "llm-cli")?;
+    let data_dir = proj_dirs.data_dir();
+    std::fs::create_dir_all(data_dir).ok()?;
+    Some(data_dir.join("chat_history.txt"))
+}
 
 pub async fn execute(
     session: Option<String>,
     model: Option<String>,
+    system: Option<String>,
 ) -> Result<()> {
-    println!("{}", "Chat command".green());
-    println!("Session: {:?}", session);
-    println!("Model: {:?}", model);
-    
-    // TODO: Implement interactive chat
-    println!("{}", "TODO: Implement interactive chat".yellow());
-    
+    let mut state = ChatState::new(session, model, system)?;
+
+    let mut editor = DefaultEditor::new()?;
+    let history_file = history_path();
+    if let Some(path) = &history_file {
+        let _ = editor.load_history(path);
+    }
+
+    println!(
+        "{} {} ({}) — session {}",
+        "Chatting with".green().bold(),
+        state.model_name.cyan(),
+        state.model().provider,
+        state.session_name.cyan()
+    );
+    if !state.history.is_empty() {
+        println!("Loaded {} previous messages", state.history.len());
+    }
+    println!(
+        "{}",
+        "/help lists commands, Ctrl-C cancels a reply, Ctrl-D or 'exit' quits".bright_black()
+    );
+
+    loop {
+        let line = match editor.readline(&format!("{} ", "you>".blue().bold())) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(input)?;
+        if input == "exit" || input == "quit" {
+            break;
+        }
+
+        let result = if input.starts_with('/') {
+            match state.handle_command(input) {
+                Ok(Flow::Retry(previous)) => {
+                    print!("{} ", "assistant>".green().bold());
+                    std::io::stdout().flush()?;
+                    state.retry(previous).await
+                }
+                Ok(Flow::Continue) => Ok(()),
+                Err(e) => Err(e),
+            }
+        } else {
+            print!("{} ", "assistant>".green().bold());
+            std::io::stdout().flush()?;
+            state.turn(input.to_string()).await
+        };
+
+        if let Err(e) = result {
+            state.formatter.print_error(&e.to_string());
+            if let Some(hint) = e.downcast_ref::<LlmCliError>().and_then(LlmCliError::hint) {
+                state.formatter.print_hint(&hint);
+            }
+        }
+    }
+
+    if let Some(path) = &history_file {
+        let _ = editor.save_history(path);
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fit_window_drops_whole_exchanges() {
+        let mut context = vec![
+            message("system", "Be brief"),
+            message("user", &"old question ".repeat(20)),
+            message("assistant", "Short answer"),
+            message("user", "Second question"),
+            message("assistant", "Second answer"),
+            message("user", "Latest question"),
+        ];
+
+        // Dropping the long question alone would fit, but leave its reply first
+        let budget = estimate_tokens(&context) - estimate_tokens(&context[1..2]);
+        fit_window(&mut context, 1, budget);
+
+        let roles: Vec<&str> = context.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user"]);
+        assert_eq!(context[1].content, "Second question");
+
+        // With no room at all, the question being asked is still sent
+        fit_window(&mut context, 1, 0);
+        assert_eq!(context.len(), 2);
+        assert_eq!(context[1].content, "Latest question");
+    }
+}
@@ -1,58 +1,87 @@
 use crate::api::client::LlmClient;
-use crate::api::models::{ChatRequest, Message};
-use crate::config::manager::ConfigManager;
-use anyhow::Context;
+use crate::api::models::{ChatRequest, ChatResponse, Message};
+use crate::config::manager::{ConfigManager, ModelInfo};
+use crate::usage::{enforce_budget, open_ledger, record_usage, PlannedCost};
 use colored::*;
 use futures::future::join_all;
 use std::time::Instant;
+
 pub async fn execute(query: String, models: Vec<String>) -> anyhow::Result<()> {
     let config_manager = ConfigManager::new()?;
+    let chat = &config_manager.get().chat;
     let mut tasks = Vec::new();
 
     println!("{}", "🚀 Comparing models...".bold().cyan());
 
+    let mut targets = Vec::new();
     for model_name in models {
-        let query_clone = query.clone();
-        let model_info = config_manager
-            .get_model_info(&model_name)
-            .context(format!("Model '{}' not found in config", model_name))?
-            .clone();
-        let api_key = config_manager.get_api_key(&model_info.provider)?;
-        let provider = model_info.provider.clone();
+        // A fallback alias compares as its whole chain, led by its first model
+        let chain = config_manager.resolve_models(&model_name)?;
+        let client = LlmClient::for_models(&config_manager, &chain)?;
+        let chain: Vec<ModelInfo> = chain.into_iter().cloned().collect();
+        let model_info = &chain[0];
 
-        tasks.push(tokio::spawn(async move {
-            let client = LlmClient::new(api_key, &provider);
+        // Each model gets its own defaults, so the comparison reflects how it is configured
+        let system = model_info.system_or(chat).map(|system| Message {
+            role: "system".to_string(),
+            content: system.to_string(),
+        });
+        let prompt = Message {
+            role: "user".to_string(),
+            content: query.clone(),
+        };
+        let request = ChatRequest {
+            model: model_info.name.clone(),
+            messages: system.into_iter().chain([prompt]).collect(),
+            temperature: Some(model_info.temperature_or(chat)),
+            max_completion_tokens: model_info.max_tokens_or(chat),
+            stream: Some(false),
+        };
+        targets.push((model_name, chain, client, request));
+    }
 
-            let request = ChatRequest {
-                model: model_name.clone(),
-                messages: vec![Message {
-                    role: "user".to_string(),
-                    content: query_clone,
-                }],
-                // FIX 1: Removed 'Some()' because your struct expects raw types
-                temperature: Some(0.7),
-                max_completion_tokens: 4096,
-                stream: Some(false),
-            };
+    // The whole comparison must fit the budget before any request goes out
+    let ledger = open_ledger();
+    let planned: Vec<PlannedCost> = targets
+        .iter()
+        .map(|(_, chain, _, request)| {
+            PlannedCost::new(chain, &request.messages, request.max_completion_tokens)
+        })
+        .collect();
+    enforce_budget(&config_manager.get().budget, ledger.as_ref(), &planned)?;
 
+    for (model_name, chain, client, request) in targets {
+        tasks.push(tokio::spawn(async move {
             let start = Instant::now();
             let response = client.chat(request).await;
             let duration = start.elapsed();
 
             // Use the unified get_text method that handles all providers
-            let response_text = match response {
-                Ok(res) => res.get_text(),
-                Err(e) => format!("Error: {}", e),
+            let (response_text, stats, usage, answered) = match response {
+                Ok(res) => (res.get_text(), stats(&res), res.usage, res.model),
+                Err(e) => (format!("Error: {}", e), String::new(), None, String::new()),
+            };
+            // Bill the model that answered, which a fallback chain may have changed
+            let model_info = chain
+                .iter()
+                .find(|model| model.name == answered)
+                .unwrap_or(&chain[0])
+                .clone();
+            let name = if model_info.name == chain[0].name {
+                model_info.name.clone()
+            } else {
+                format!("{} (fallback for {})", model_info.name, model_name)
             };
 
-            Ok::<(String, String, std::time::Duration), anyhow::Error>((model_name, response_text, duration))
+            Ok::<_, anyhow::Error>((name, model_info, response_text, duration, stats, usage))
         }));
     }
 
     let results = join_all(tasks).await;
 
     for task_result in results {
-        if let Ok(Ok((name, response_text, duration))) = task_result {
+        if let Ok(Ok((name, model_info, response_text, duration, stats, usage))) = task_result {
+            record_usage(ledger.as_ref(), "compare", &model_info, usage, None);
             println!(
                 "\n{}",
                 format!("--- MODEL: {} ({:?}) ---", name, duration)
@@ -60,9 +89,24 @@ pub async fn execute(query: String, models: Vec<String>) -> anyhow::Result<()> {
                     .bold()
             );
             println!("{}", response_text);
+            if !stats.is_empty() {
+                println!("{}", stats.bright_black());
+            }
             println!("{}", "-".repeat(50).bright_black());
         }
     }
 
     Ok(())
 }
+
+/// Token usage and finish reason of one model's answer, as a summary line.
+fn stats(response: &ChatResponse) -> String {
+    let mut parts = Vec::new();
+    if let Some(usage) = &response.usage {
+        parts.push(format!("tokens: {}", usage));
+    }
+    if let Some(reason) = &response.finish_reason {
+        parts.push(format!("finish: {}", reason));
+    }
+    parts.join(" · ")
+}
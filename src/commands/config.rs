@@ -24,7 +24,25 @@ pub fn execute(action: ConfigAction) -> Result<()> {
             let available_models = config_manager.get_available_models();
             println!("{}", "Available Models:".green().bold());
             for model in available_models {
-                println!("  {} ({}) - {}", model.name.cyan(), model.provider, model.display_name);
+                let aliases = if model.aliases.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [aliases: {}]", model.aliases.join(", "))
+                };
+                println!(
+                    "  {} ({}) - {}{}",
+                    model.name.cyan(),
+                    model.provider,
+                    model.display_name,
+                    aliases
+                );
+            }
+            let fallbacks = &config_manager.get().models.fallbacks;
+            if !fallbacks.is_empty() {
+                println!("{}", "Fallback Chains:".green().bold());
+                for (alias, models) in fallbacks {
+                    println!("  {} → {}", alias.cyan(), models.join(" → "));
+                }
             }
         }
         ConfigAction::Reset => {
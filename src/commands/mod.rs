@@ -4,3 +4,4 @@ pub mod config;
 pub mod session;
 pub mod template;
 pub mod compare; 
+pub mod usage;
@@ -1,54 +1,411 @@
 use crate::cli::SessionAction;
-use crate::session::SessionStore;
+use crate::session::{import, SearchHit, SearchQuery, Session, SessionStore};
 use anyhow::Result;
+use chrono::TimeZone;
 use colored::*;
+use std::collections::HashSet;
 
 pub fn execute(action: SessionAction) -> Result<()> {
     let store = SessionStore::new()?;
     
     match action {
-        SessionAction::List => {
-            let sessions = store.list_sessions()?;
+        SessionAction::List { tags } => {
+            let mut sessions = Vec::new();
+            for name in store.list_sessions()? {
+                if let Some(session) = store.load_session(&name)? {
+                    if tags.iter().all(|tag| session.tags.contains(tag)) {
+                        sessions.push(session);
+                    }
+                }
+            }
             if sessions.is_empty() {
                 println!("{}", "No sessions found".yellow());
             } else {
-                println!("{}", "Sessions:".green().bold());
-                for session in sessions {
-                    println!("  • {}", session.cyan());
-                }
+                print_sessions(&sessions);
             }
         }
-        SessionAction::Show { name } => {
+        SessionAction::Show { name, tree } => {
             match store.load_session(&name)? {
                 Some(session) => {
                     println!("{} {}", "Session:".green().bold(), name.cyan());
-                    println!("Messages: {}", session.messages.len());
+                    if let Some(title) = &session.title {
+                        println!("Title: {}", title);
+                    }
+                    if let Some(description) = &session.description {
+                        println!("Description: {}", description);
+                    }
+                    if !session.tags.is_empty() {
+                        println!("Tags: {}", session.tags.join(", "));
+                    }
+                    println!("Messages: {}", session.active_ids().len());
+                    let branches = session.branch_count();
+                    if branches > 1 {
+                        println!("Branches: {}", branches);
+                    }
+                    // Every branch was paid for, so count them all
+                    let usages: Vec<_> = session
+                        .nodes
+                        .iter()
+                        .filter_map(|n| n.message.usage)
+                        .collect();
+                    if !usages.is_empty() {
+                        let input: u64 = usages.iter().map(|u| u.input).sum();
+                        let output: u64 = usages.iter().map(|u| u.output).sum();
+                        println!("Tokens: {} in, {} out", input, output);
+                    }
+                    let models = models_used(&session);
+                    if !models.is_empty() {
+                        println!("Models: {}", models.join(", "));
+                    }
                     println!("Created: {}", chrono::DateTime::from_timestamp(session.created_at, 0)
                         .map(|dt| dt.to_rfc2822())
                         .unwrap_or_else(|| "Unknown".to_string()));
+                    if tree {
+                        println!();
+                        print_tree(&session);
+                    }
                 }
                 None => {
                     println!("{} Session '{}' not found", "✗".red(), name);
                 }
             }
         }
+        SessionAction::Search {
+            query,
+            regex,
+            role,
+            model,
+            since,
+            until,
+        } => {
+            let hits = store.search(&SearchQuery {
+                text: query.clone(),
+                regex,
+                role,
+                since,
+                until,
+                model,
+            })?;
+            if hits.is_empty() {
+                println!("{}", format!("No messages match '{}'", query).yellow());
+                return Ok(());
+            }
+
+            let sessions: HashSet<&str> = hits.iter().map(|hit| hit.session.as_str()).collect();
+            println!(
+                "{}",
+                format!(
+                    "{} matching messages in {} sessions:",
+                    hits.len(),
+                    sessions.len()
+                )
+                .green()
+                .bold()
+            );
+            for hit in &hits {
+                println!(
+                    "  {} #{} {}: {}",
+                    hit.session.cyan(),
+                    hit.id,
+                    hit.message.role,
+                    snippet(hit)
+                );
+            }
+        }
+        SessionAction::Fork { name, at, new_name } => match store.load_session(&name)? {
+            Some(session) => {
+                if store.load_session(&new_name)?.is_some() {
+                    println!("{} Session '{}' already exists", "✗".red(), new_name);
+                    return Ok(());
+                }
+                let fork = session.fork(at, &new_name)?;
+                store.save_session(&fork)?;
+                println!(
+                    "{} Forked '{}' at message #{} into '{}' ({} messages)",
+                    "✓".green(),
+                    name,
+                    at,
+                    new_name.cyan(),
+                    fork.nodes.len()
+                );
+            }
+            None => {
+                println!("{} Session '{}' not found", "✗".red(), name);
+            }
+        },
+        SessionAction::Edit { name, at, content } => match store.load_session(&name)? {
+            Some(mut session) => {
+                let id = session.edit(at, content)?;
+                session.updated_at = chrono::Utc::now().timestamp();
+                store.save_session(&session)?;
+                println!(
+                    "{} Added message #{} as a new branch; get it answered with `chat --session {}` and `/retry`",
+                    "✓".green(),
+                    id,
+                    name
+                );
+            }
+            None => {
+                println!("{} Session '{}' not found", "✗".red(), name);
+            }
+        },
+        SessionAction::Tag { name, tags, remove } => match store.load_session(&name)? {
+            Some(mut session) => {
+                for tag in tags {
+                    let tag = tag.trim().to_string();
+                    if remove {
+                        session.tags.retain(|t| *t != tag);
+                    } else if !tag.is_empty() && !session.tags.contains(&tag) {
+                        session.tags.push(tag);
+                    }
+                }
+                session.tags.sort();
+                store.save_session(&session)?;
+                if session.tags.is_empty() {
+                    println!("{} Session '{}' has no tags", "✓".green(), name);
+                } else {
+                    println!(
+                        "{} Tagged '{}': {}",
+                        "✓".green(),
+                        name,
+                        session.tags.join(", ")
+                    );
+                }
+            }
+            None => {
+                println!("{} Session '{}' not found", "✗".red(), name);
+            }
+        },
+        SessionAction::Describe {
+            name,
+            description,
+            title,
+        } => match store.load_session(&name)? {
+            Some(mut session) => {
+                if description.is_some() {
+                    session.description = description;
+                }
+                if title.is_some() {
+                    session.title = title;
+                }
+                store.save_session(&session)?;
+                println!("{} Updated session '{}'", "✓".green(), name);
+            }
+            None => {
+                println!("{} Session '{}' not found", "✗".red(), name);
+            }
+        },
         SessionAction::Delete { name } => {
             store.delete_session(&name)?;
             println!("{} Deleted session '{}'", "✓".green(), name);
         }
-        SessionAction::Export { name, output } => {
-            match store.load_session(&name)? {
-                Some(session) => {
-                    let json = serde_json::to_string_pretty(&session)?;
-                    std::fs::write(&output, json)?;
-                    println!("{} Exported session to {}", "✓".green(), output.cyan());
-                }
-                None => {
-                    println!("{} Session '{}' not found", "✗".red(), name);
+        SessionAction::Export {
+            name,
+            output,
+            format,
+        } => match store.load_session(&name)? {
+            Some(session) => {
+                std::fs::write(&output, session.export(format)?)?;
+                println!("{} Exported session to {}", "✓".green(), output.cyan());
+            }
+            None => {
+                println!("{} Session '{}' not found", "✗".red(), name);
+            }
+        },
+        SessionAction::Import { file, name } => {
+            let sessions = import(&file)?;
+            let count = sessions.len();
+            for (i, mut session) in sessions.into_iter().enumerate() {
+                let base = match &name {
+                    Some(name) if count > 1 => format!("{}-{}", name, i + 1),
+                    Some(name) => name.clone(),
+                    None => session.name.clone(),
+                };
+                // Never overwrite a stored session
+                session.name = base.clone();
+                let mut n = 1;
+                while store.load_session(&session.name)?.is_some() {
+                    n += 1;
+                    session.name = format!("{}-{}", base, n);
                 }
+                store.save_session(&session)?;
+                println!(
+                    "{} Imported '{}' ({} messages)",
+                    "✓".green(),
+                    session.name.cyan(),
+                    session.nodes.len()
+                );
             }
         }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Lists sessions as a table, most recently updated first.
+fn print_sessions(sessions: &[Session]) {
+    const TITLE_WIDTH: usize = 40;
+    let mut sessions: Vec<&Session> = sessions.iter().collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+
+    let width = sessions
+        .iter()
+        .map(|s| s.name.chars().count())
+        .chain(["SESSION".len()])
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{}",
+        format!(
+            "{:<width$}  {:<TITLE_WIDTH$}  {:>8}  {:<16}  {}",
+            "SESSION", "TITLE", "MESSAGES", "UPDATED", "TAGS"
+        )
+        .bold()
+    );
+    for session in sessions {
+        let title = session.title.as_deref().unwrap_or_default();
+        let title = if title.chars().count() > TITLE_WIDTH {
+            title.chars().take(TITLE_WIDTH - 1).collect::<String>() + "…"
+        } else {
+            title.to_string()
+        };
+        let updated = chrono::Local
+            .timestamp_opt(session.updated_at, 0)
+            .single()
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{}  {:<TITLE_WIDTH$}  {:>8}  {:<16}  {}",
+            format!("{:<width$}", session.name).cyan(),
+            title,
+            session.active_ids().len(),
+            updated,
+            session.tags.join(", ")
+        );
+    }
+}
+
+/// The distinct models that wrote replies on any branch, as "model (provider)".
+fn models_used(session: &Session) -> Vec<String> {
+    let mut models = Vec::new();
+    for node in &session.nodes {
+        let message = &node.message;
+        let Some(model) = &message.model else {
+            continue;
+        };
+        let label = match &message.provider {
+            Some(provider) => format!("{} ({})", model, provider),
+            None => model.clone(),
+        };
+        if !models.contains(&label) {
+            models.push(label);
+        }
+    }
+    models
+}
+
+/// The match with up to `SNIPPET_CONTEXT` characters either side, on one
+/// line, with the match highlighted.
+fn snippet(hit: &SearchHit) -> String {
+    const SNIPPET_CONTEXT: usize = 40;
+    let content = &hit.message.content;
+    let flatten = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let before: Vec<char> = content[..hit.matched.start].chars().collect();
+    let after: Vec<char> = content[hit.matched.end..].chars().collect();
+    let mut snippet = String::new();
+    if before.len() > SNIPPET_CONTEXT {
+        snippet.push('…');
+    }
+    let before: String = before[before.len().saturating_sub(SNIPPET_CONTEXT)..]
+        .iter()
+        .collect();
+    let after_text: String = after.iter().take(SNIPPET_CONTEXT).collect();
+
+    // Keep the spaces around the match that flattening would trim
+    snippet.push_str(&flatten(&before));
+    if before.ends_with(char::is_whitespace) {
+        snippet.push(' ');
+    }
+    snippet.push_str(
+        &flatten(&content[hit.matched.clone()])
+            .yellow()
+            .bold()
+            .to_string(),
+    );
+    if after_text.starts_with(char::is_whitespace) {
+        snippet.push(' ');
+    }
+    snippet.push_str(&flatten(&after_text));
+    if after.len() > SNIPPET_CONTEXT {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Draws the conversation tree. A branch's messages line up under each other
+/// and only a fork indents, so long linear chats stay readable; messages off
+/// the active branch are dimmed.
+fn print_tree(session: &Session) {
+    let active: HashSet<usize> = session.active_ids().into_iter().collect();
+    print_forks(session, session.children(None), "", &active);
+}
+
+fn print_forks(session: &Session, ids: Vec<usize>, prefix: &str, active: &HashSet<usize>) {
+    if let [id] = ids[..] {
+        print_branch(session, id, prefix, prefix, active);
+        return;
+    }
+    for (i, &id) in ids.iter().enumerate() {
+        let last = i + 1 == ids.len();
+        let (first, rest) = if last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        print_branch(
+            session,
+            id,
+            &format!("{}{}", prefix, first),
+            &format!("{}{}", prefix, rest),
+            active,
+        );
+    }
+}
+
+fn print_branch(
+    session: &Session,
+    mut id: usize,
+    first_prefix: &str,
+    prefix: &str,
+    active: &HashSet<usize>,
+) {
+    let mut line_prefix = first_prefix;
+    loop {
+        let message = &session.nodes[id].message;
+        let text = message.content.lines().next().unwrap_or_default();
+        let mut preview: String = text.chars().take(60).collect();
+        if preview.len() < message.content.len() {
+            preview.push('…');
+        }
+        let line = match &message.model {
+            Some(model) => format!("#{} {} ({}): {}", id, message.role, model, preview),
+            None => format!("#{} {}: {}", id, message.role, preview),
+        };
+        if active.contains(&id) {
+            println!("{}{}", line_prefix, line);
+        } else {
+            println!("{}{}", line_prefix, line.bright_black());
+        }
+
+        let children = session.children(Some(id));
+        match children[..] {
+            [] => return,
+            [child] => {
+                id = child;
+                line_prefix = prefix;
+            }
+            _ => return print_forks(session, children, prefix, active),
+        }
+    }
+}
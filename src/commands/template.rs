@@ -1,30 +1,155 @@
 use crate::cli::TemplateAction;
-use anyhow::Result;
+use crate::template::{FrontMatter, TemplateScope, TemplateStore};
+use anyhow::{Context, Result};
 use colored::*;
+use std::io::{IsTerminal, Read};
 
 pub fn execute(action: TemplateAction) -> Result<()> {
+    let store = TemplateStore::new()?;
+
     match action {
-        TemplateAction::Create { name } => {
-            println!("{} Creating template: {}", "→".blue(), name.cyan());
-            // TODO: Implement template creation
-            println!("{}", "TODO: Implement template creation".yellow());
+        TemplateAction::Create { name, global } => {
+            let scope = if global {
+                TemplateScope::Global
+            } else {
+                TemplateScope::Project
+            };
+
+            let content = if std::io::stdin().is_terminal() {
+                let existing = store.load(&name)?.unwrap_or_default();
+                edit_in_editor(&name, &existing)?
+            } else {
+                let mut content = String::new();
+                std::io::stdin().read_to_string(&mut content)?;
+                content
+            };
+
+            if content.trim().is_empty() {
+                println!("{} Template is empty, nothing saved", "✗".red());
+                return Ok(());
+            }
+
+            let path = store.save(&name, &content, scope)?;
+            println!(
+                "{} Saved template '{}' to {}",
+                "✓".green(),
+                name.cyan(),
+                path.display()
+            );
         }
         TemplateAction::List => {
-            println!("{}", "Templates:".green().bold());
-            // TODO: Implement template listing
-            println!("{}", "TODO: Implement template listing".yellow());
-        }
-        TemplateAction::Show { name } => {
-            println!("{} Showing template: {}", "→".blue(), name.cyan());
-            // TODO: Implement template display
-            println!("{}", "TODO: Implement template display".yellow());
+            let templates = store.list()?;
+            if templates.is_empty() {
+                println!("{}", "No templates found".yellow());
+            } else {
+                println!("{}", "Templates:".green().bold());
+                for template in templates {
+                    println!("  • {} ({})", template.name.cyan(), template.scope);
+                }
+            }
         }
-        TemplateAction::Delete { name } => {
-            println!("{} Deleting template: {}", "→".blue(), name.cyan());
-            // TODO: Implement template deletion
-            println!("{}", "TODO: Implement template deletion".yellow());
+        TemplateAction::Show { name } => match store.find(&name)? {
+            Some(entry) => {
+                let content = std::fs::read_to_string(&entry.path)?;
+                let (front_matter, body) = FrontMatter::split(&content)?;
+
+                println!(
+                    "{} {} ({})",
+                    "Template:".green().bold(),
+                    name.cyan(),
+                    entry.path.display()
+                );
+                print_front_matter(&front_matter);
+                println!("{}", body);
+            }
+            None => {
+                println!("{} Template '{}' not found", "✗".red(), name);
+            }
+        },
+        TemplateAction::Delete { name, global } => {
+            let scope = global.then_some(TemplateScope::Global);
+            match store.delete(&name, scope)? {
+                Some(entry) => println!(
+                    "{} Deleted {} template '{}'",
+                    "✓".green(),
+                    entry.scope,
+                    name
+                ),
+                None => println!("{} Template '{}' not found", "✗".red(), name),
+            }
         }
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+
+fn print_front_matter(front_matter: &FrontMatter) {
+    if let Some(description) = &front_matter.description {
+        println!("{}", description);
+    }
+
+    let settings = [
+        ("Model", front_matter.model.clone()),
+        (
+            "Temperature",
+            front_matter.temperature.map(|t| t.to_string()),
+        ),
+        ("Max tokens", front_matter.max_tokens.map(|t| t.to_string())),
+        ("System", front_matter.system.clone()),
+    ];
+    for (label, value) in settings {
+        if let Some(value) = value {
+            println!("{}: {}", label, value);
+        }
+    }
+
+    if !front_matter.variables.is_empty() {
+        println!("{}", "Variables:".green().bold());
+        for spec in &front_matter.variables {
+            let requirement = match (&spec.default, spec.is_required()) {
+                (Some(default), _) => format!("default: {}", default),
+                (None, true) => "required".to_string(),
+                (None, false) => "optional".to_string(),
+            };
+            print!("  {} ({}, {})", spec.name.cyan(), spec.kind, requirement);
+            match &spec.description {
+                Some(description) => println!(" - {}", description),
+                None => println!(),
+            }
+        }
+    }
+
+    println!("{}", "-".repeat(50).bright_black());
+}
+
+/// Opens `$VISUAL`/`$EDITOR` on a scratch file seeded with `initial` and
+/// returns what the user saved.
+fn edit_in_editor(name: &str, initial: &str) -> Result<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let path = std::env::temp_dir().join(format!(
+        "llm-cli-template-{}-{}.tmpl",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, initial)?;
+
+    // The editor may carry its own arguments, e.g. EDITOR="code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("Editor command is empty")?;
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status()
+        .context(format!("Failed to launch editor '{}'", editor))?;
+
+    let content = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}", status);
+    }
+    Ok(content?)
+}
@@ -0,0 +1,75 @@
+use crate::usage::{record_day, summarize, GroupBy, UsageLedger, UsageSummary};
+use anyhow::Result;
+use chrono::NaiveDate;
+use colored::*;
+
+pub fn execute(by: GroupBy, since: Option<NaiveDate>, until: Option<NaiveDate>) -> Result<()> {
+    let ledger = UsageLedger::new()?;
+    let records: Vec<_> = ledger
+        .records()?
+        .into_iter()
+        .filter(|record| {
+            let day = record_day(record);
+            since.is_none_or(|since| day.is_some_and(|day| day >= since))
+                && until.is_none_or(|until| day.is_some_and(|day| day <= until))
+        })
+        .collect();
+
+    if records.is_empty() {
+        println!("{}", "No usage recorded".yellow());
+        return Ok(());
+    }
+
+    let (rows, total) = summarize(&records, by);
+    let heading = match by {
+        GroupBy::Day => "DAY",
+        GroupBy::Model => "MODEL",
+        GroupBy::Provider => "PROVIDER",
+        GroupBy::Session => "SESSION",
+    };
+    let width = rows
+        .iter()
+        .map(|row| row.key.len())
+        .chain([heading.len(), total.key.len()])
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "{}",
+        format!(
+            "{:<width$}  {:>8}  {:>12}  {:>12}  {:>10}",
+            heading, "REQUESTS", "INPUT", "OUTPUT", "COST"
+        )
+        .bold()
+    );
+    for row in &rows {
+        println!("{}", format_row(row, width));
+    }
+    println!("{}", format_row(&total, width).green().bold());
+
+    if total.unpriced > 0 {
+        println!(
+            "\n{}",
+            format!(
+                "{} request(s) used models without prices; set input_price and output_price \
+                 (USD per million tokens) on them in models.available",
+                total.unpriced
+            )
+            .bright_black()
+        );
+    }
+
+    Ok(())
+}
+
+fn format_row(row: &UsageSummary, width: usize) -> String {
+    let cost = if row.unpriced == row.requests {
+        "-".to_string()
+    } else {
+        format!("${:.4}", row.cost)
+    };
+    format!(
+        "{:<width$}  {:>8}  {:>12}  {:>12}  {:>10}",
+        row.key, row.requests, row.input, row.output, cost
+    )
+}
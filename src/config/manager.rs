@@ -1,5 +1,8 @@
+use crate::api::Usage;
+use crate::utils::LlmCliError;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[allow(dead_code)]
@@ -10,6 +13,12 @@ pub struct Config {
     pub chat: ChatConfig,
     pub session: SessionConfig,
     pub output: OutputConfig,
+    #[serde(default, skip_serializing_if = "BudgetConfig::is_unset")]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default, skip_serializing_if = "HttpConfig::is_unset")]
+    pub http: HttpConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,24 +26,81 @@ pub struct ApiConfig {
     pub providers: ProviderConfigs,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ProviderConfigs {
-    pub openai: ProviderConfig,
-    pub anthropic: ProviderConfig,
-    pub google: ProviderConfig,
+/// Named provider endpoints, keyed by the name `models.available` entries use.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct ProviderConfigs(BTreeMap<String, ProviderConfig>);
+
+impl ProviderConfigs {
+    pub fn get(&self, provider: &str) -> Option<&ProviderConfig> {
+        self.0.get(provider)
+    }
+
+    pub fn get_mut(&mut self, provider: &str) -> Option<&mut ProviderConfig> {
+        self.0.get_mut(provider)
+    }
+
+    pub fn insert(&mut self, provider: &str, config: ProviderConfig) {
+        self.0.insert(provider.to_string(), config);
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProviderConfig {
     pub api_key: Option<String>,
-    pub api_key_env: String,
+    /// Environment variable holding the key; leave unset for keyless local servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
     pub enabled: bool,
+    /// Overrides the protocol's default API root (e.g. `http://localhost:11434/v1`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Wire protocol spoken by the endpoint: `openai`, `anthropic` or `google`.
+    /// Defaults to the provider's own name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    /// HTTP settings for this provider only, overriding the global `[http]` ones
+    #[serde(default, skip_serializing_if = "HttpConfig::is_unset")]
+    pub http: HttpConfig,
+}
+
+impl ProviderConfig {
+    fn hosted(api_key_env: &str, enabled: bool) -> Self {
+        Self {
+            api_key: None,
+            api_key_env: Some(api_key_env.to_string()),
+            enabled,
+            base_url: None,
+            protocol: None,
+            http: HttpConfig::default(),
+        }
+    }
+
+    /// A keyless OpenAI-compatible endpoint, the common shape for local servers.
+    fn local(base_url: &str) -> Self {
+        Self {
+            api_key: None,
+            api_key_env: None,
+            enabled: true,
+            base_url: Some(base_url.to_string()),
+            protocol: Some("openai".to_string()),
+            http: HttpConfig::default(),
+        }
+    }
+
+    pub fn protocol<'a>(&'a self, provider: &'a str) -> &'a str {
+        self.protocol.as_deref().unwrap_or(provider)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModelConfig {
     pub default: String,
     pub available: Vec<ModelInfo>,
+    /// Aliases for ordered lists of models to try in turn, e.g.
+    /// `fast = ["gemini-3-flash", "gpt-4o-mini", "claude-3-haiku-20240307"]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fallbacks: BTreeMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,6 +108,92 @@ pub struct ModelInfo {
     pub name: String,
     pub provider: String,
     pub display_name: String,
+    /// Short names accepted wherever a model name is, e.g. `sonnet`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Price in USD per million input tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_price: Option<f64>,
+    /// Price in USD per million output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_price: Option<f64>,
+    /// Price in USD per million cached input tokens; defaults to `input_price`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_price: Option<f64>,
+    /// Sampling temperature for this model; defaults to `chat.temperature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Reply token limit for this model; defaults to `chat.max_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Tokens the model can attend to; chat drops the oldest turns to fit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+    /// System prompt sent with every request to this model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+}
+
+impl ModelInfo {
+    fn priced(name: &str, provider: &str, display_name: &str, prices: [f64; 3]) -> Self {
+        let [input, output, cached] = prices;
+        Self {
+            name: name.to_string(),
+            provider: provider.to_string(),
+            display_name: display_name.to_string(),
+            aliases: Vec::new(),
+            input_price: Some(input),
+            output_price: Some(output),
+            cached_price: Some(cached),
+            temperature: None,
+            max_tokens: None,
+            context_window: None,
+            system: None,
+        }
+    }
+
+    fn aliased(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    /// This model's temperature, falling back to `chat.temperature`.
+    pub fn temperature_or(&self, chat: &ChatConfig) -> f32 {
+        self.temperature.unwrap_or(chat.temperature)
+    }
+
+    /// This model's system prompt, falling back to `chat.system`.
+    pub fn system_or<'a>(&'a self, chat: &'a ChatConfig) -> Option<&'a str> {
+        self.system.as_deref().or(chat.system.as_deref())
+    }
+
+    /// This model's reply token limit, falling back to `chat.max_tokens`.
+    pub fn max_tokens_or(&self, chat: &ChatConfig) -> u32 {
+        self.max_tokens.unwrap_or(chat.max_tokens)
+    }
+
+    /// The reply token limit for a request asking for `requested`, capped at
+    /// this model's own `max_tokens` if it declares one.
+    pub fn reply_limit(&self, requested: u32) -> u32 {
+        self.max_tokens.map_or(requested, |limit| limit.min(requested))
+    }
+
+    /// Cost in USD of a response with the given usage, or `None` when the
+    /// model has no prices configured.
+    pub fn cost(&self, usage: &Usage) -> Option<f64> {
+        if self.input_price.is_none() && self.output_price.is_none() {
+            return None;
+        }
+
+        let input_price = self.input_price.unwrap_or(0.0);
+        let cached_price = self.cached_price.unwrap_or(input_price);
+        let uncached = usage.input.saturating_sub(usage.cached);
+
+        let cost = uncached as f64 * input_price
+            + usage.cached as f64 * cached_price
+            + usage.output as f64 * self.output_price.unwrap_or(0.0);
+        Some(cost / 1_000_000.0)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,6 +201,9 @@ pub struct ChatConfig {
     pub temperature: f32,
     pub max_tokens: u32,
     pub streaming: bool,
+    /// System prompt for models without one of their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -63,68 +218,194 @@ pub struct OutputConfig {
     pub markdown_rendering: bool,
 }
 
+/// Settings for the HTTP client that talks to providers.
+///
+/// Set globally under `[http]` or per provider under
+/// `[api.providers.<name>.http]`. Timeouts are in seconds; 0 disables one.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HttpConfig {
+    /// Time allowed to establish a connection (default 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+    /// Time the server may stay silent, before replying or mid-stream (default 300)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout_secs: Option<u64>,
+    /// Time allowed for a whole request, including a streamed reply (default none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Proxy for all requests, e.g. `http://proxy.internal:3128`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file with additional CA certificates to trust
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// Headers sent with every request
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl HttpConfig {
+    fn is_unset(&self) -> bool {
+        self.connect_timeout_secs.is_none()
+            && self.read_timeout_secs.is_none()
+            && self.timeout_secs.is_none()
+            && self.proxy.is_none()
+            && self.ca_bundle.is_none()
+            && self.headers.is_empty()
+    }
+
+    /// These settings with `overrides` applied on top; headers are merged,
+    /// with `overrides` winning on conflicts.
+    pub fn merged(&self, overrides: &HttpConfig) -> HttpConfig {
+        let mut headers = self.headers.clone();
+        headers.extend(overrides.headers.clone());
+
+        HttpConfig {
+            connect_timeout_secs: overrides.connect_timeout_secs.or(self.connect_timeout_secs),
+            read_timeout_secs: overrides.read_timeout_secs.or(self.read_timeout_secs),
+            timeout_secs: overrides.timeout_secs.or(self.timeout_secs),
+            proxy: overrides.proxy.clone().or_else(|| self.proxy.clone()),
+            ca_bundle: overrides
+                .ca_bundle
+                .clone()
+                .or_else(|| self.ca_bundle.clone()),
+            headers,
+        }
+    }
+}
+
+/// How `LlmClient` retries rate-limited, overloaded and dropped requests.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total tries per request, including the first; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each one after
+    pub initial_delay_ms: u64,
+    /// Upper bound for any single delay, including server-requested ones
+    pub max_delay_ms: u64,
+    /// Fraction of each delay that is randomized (0.0 to 1.0) so clients
+    /// hitting the same limit do not retry in lockstep
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay_ms: 500,
+            max_delay_ms: 30_000,
+            jitter: 0.25,
+        }
+    }
+}
+
+/// Spending limits in USD, checked against the usage ledger before requests are sent.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BudgetConfig {
+    #[serde(flatten)]
+    pub limits: BudgetLimits,
+    /// What to do when a request could exceed a limit
+    #[serde(default)]
+    pub on_exceed: BudgetAction,
+    /// Limits for individual providers, enforced in addition to the global ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub providers: BTreeMap<String, BudgetLimits>,
+}
+
+impl BudgetConfig {
+    fn is_unset(&self) -> bool {
+        self.limits.is_unset()
+            && self.providers.is_empty()
+            && self.on_exceed == BudgetAction::Refuse
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BudgetLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<f64>,
+}
+
+impl BudgetLimits {
+    pub fn is_unset(&self) -> bool {
+        self.daily.is_none() && self.monthly.is_none()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Fail with `BudgetExceeded`
+    #[default]
+    Refuse,
+    /// Ask for confirmation on a terminal; refuse otherwise
+    Prompt,
+}
+
+impl std::str::FromStr for BudgetAction {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "refuse" => Ok(BudgetAction::Refuse),
+            "prompt" => Ok(BudgetAction::Prompt),
+            _ => anyhow::bail!("Invalid budget action '{}': use refuse or prompt", value),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             api: ApiConfig {
-                providers: ProviderConfigs {
-                    openai: ProviderConfig {
-                        api_key: None,
-                        api_key_env: "OPENAI_API_KEY".to_string(),
-                        enabled: true,
-                    },
-                    anthropic: ProviderConfig {
-                        api_key: None,
-                        api_key_env: "ANTHROPIC_API_KEY".to_string(),
-                        enabled: false,
-                    },
-                    google: ProviderConfig {
-                        api_key: None,
-                        api_key_env: "GOOGLE_API_KEY".to_string(),
-                        enabled: true,
-                    },
-                },
+                providers: ProviderConfigs(BTreeMap::from([
+                    (
+                        "openai".to_string(),
+                        ProviderConfig::hosted("OPENAI_API_KEY", true),
+                    ),
+                    (
+                        "anthropic".to_string(),
+                        ProviderConfig::hosted("ANTHROPIC_API_KEY", false),
+                    ),
+                    (
+                        "google".to_string(),
+                        ProviderConfig::hosted("GOOGLE_API_KEY", true),
+                    ),
+                ])),
             },
             models: ModelConfig {
                 // Setting GPT-4o as the new default
                 default: "gpt-4o".to_string(),
                 available: vec![
-                    ModelInfo {
-                        name: "gpt-4o".to_string(),
-                        provider: "openai".to_string(),
-                        display_name: "GPT-4o".to_string(),
-                    },
-                    ModelInfo {
-                        name: "gpt-4".to_string(),
-                        provider: "openai".to_string(),
-                        display_name: "GPT-4".to_string(),
-                    },
-                    ModelInfo {
-                        name: "gpt-3.5-turbo".to_string(),
-                        provider: "openai".to_string(),
-                        display_name: "GPT-3.5 Turbo".to_string(),
-                    },
-                    ModelInfo {
-                        name: "claude-3-5-sonnet-20241022".to_string(),
-                        provider: "anthropic".to_string(),
-                        display_name: "Claude 3.5 Sonnet".to_string(),
-                    },
-                    ModelInfo {
-                        name: "claude-3-haiku-20240307".to_string(),
-                        provider: "anthropic".to_string(),
-                        display_name: "Claude 3 Haiku".to_string(),
-                    },
-                    ModelInfo {
-                        name: "gemini-pro".to_string(),
-                        provider: "google".to_string(),
-                        display_name: "Gemini Pro".to_string(),
-                    },
+                    ModelInfo::priced("gpt-4o", "openai", "GPT-4o", [2.5, 10.0, 1.25]),
+                    ModelInfo::priced("gpt-4", "openai", "GPT-4", [30.0, 60.0, 30.0]),
+                    ModelInfo::priced("gpt-3.5-turbo", "openai", "GPT-3.5 Turbo", [0.5, 1.5, 0.5]),
+                    ModelInfo::priced(
+                        "claude-3-5-sonnet-20241022",
+                        "anthropic",
+                        "Claude 3.5 Sonnet",
+                        [3.0, 15.0, 0.3],
+                    )
+                    .aliased("sonnet"),
+                    ModelInfo::priced(
+                        "claude-3-haiku-20240307",
+                        "anthropic",
+                        "Claude 3 Haiku",
+                        [0.25, 1.25, 0.03],
+                    )
+                    .aliased("haiku"),
+                    ModelInfo::priced("gemini-pro", "google", "Gemini Pro", [0.5, 1.5, 0.5]),
                 ],
+                fallbacks: BTreeMap::new(),
             },
             chat: ChatConfig {
                 temperature: 0.7,
                 max_tokens: 4096,
                 streaming: true,
+                system: None,
             },
             session: SessionConfig {
                 auto_save: true,
@@ -134,6 +415,9 @@ impl Default for Config {
                 syntax_highlighting: true,
                 markdown_rendering: true,
             },
+            budget: BudgetConfig::default(),
+            retry: RetryConfig::default(),
+            http: HttpConfig::default(),
         }
     }
 }
@@ -180,27 +464,70 @@ impl ConfigManager {
         let parts: Vec<&str> = key.split('.').collect();
 
         match parts.as_slice() {
-            ["api", "providers", provider, "api_key"] => match *provider {
-                "openai" => self.config.api.providers.openai.api_key = Some(value.to_string()),
-                "anthropic" => {
-                    self.config.api.providers.anthropic.api_key = Some(value.to_string())
-                }
-                "google" => self.config.api.providers.google.api_key = Some(value.to_string()),
-                _ => anyhow::bail!("Unknown provider: {}", provider),
-            },
+            ["api", "providers", provider, "api_key"] => {
+                self.provider_config_mut(provider)?.api_key = Some(value.to_string())
+            }
+            ["api", "providers", provider, "api_key_env"] => {
+                self.provider_config_mut(provider)?.api_key_env = Some(value.to_string())
+            }
             ["api", "providers", provider, "enabled"] => {
-                let enabled: bool = value.parse()?;
-                match *provider {
-                    "openai" => self.config.api.providers.openai.enabled = enabled,
-                    "anthropic" => self.config.api.providers.anthropic.enabled = enabled,
-                    "google" => self.config.api.providers.google.enabled = enabled,
-                    _ => anyhow::bail!("Unknown provider: {}", provider),
+                self.provider_config_mut(provider)?.enabled = value.parse()?
+            }
+            ["api", "providers", provider, "protocol"] => {
+                self.provider_config_mut(provider)?.protocol = Some(value.to_string())
+            }
+            ["api", "providers", provider, "base_url"] => {
+                // Pointing a new name at a URL registers it as a local OpenAI-compatible server
+                match self.config.api.providers.get_mut(provider) {
+                    Some(config) => config.base_url = Some(value.to_string()),
+                    None => self
+                        .config
+                        .api
+                        .providers
+                        .insert(provider, ProviderConfig::local(value)),
                 }
             }
             ["models", "default"] => self.config.models.default = value.to_string(),
+            ["models", "fallbacks", alias] => {
+                let models = value.split(',').map(|m| m.trim().to_string()).collect();
+                self.config
+                    .models
+                    .fallbacks
+                    .insert(alias.to_string(), models);
+            }
             ["chat", "temperature"] => self.config.chat.temperature = value.parse()?,
             ["chat", "max_tokens"] => self.config.chat.max_tokens = value.parse()?,
             ["chat", "streaming"] => self.config.chat.streaming = value.parse()?,
+            ["chat", "system"] => {
+                self.config.chat.system = Some(value.to_string()).filter(|s| !s.is_empty())
+            }
+            ["http", key] => Self::set_http(&mut self.config.http, key, value)?,
+            ["http", "headers", name] => {
+                self.config
+                    .http
+                    .headers
+                    .insert(name.to_string(), value.to_string());
+            }
+            ["api", "providers", provider, "http", key] => {
+                Self::set_http(&mut self.provider_config_mut(provider)?.http, key, value)?
+            }
+            ["api", "providers", provider, "http", "headers", name] => {
+                let http = &mut self.provider_config_mut(provider)?.http;
+                http.headers.insert(name.to_string(), value.to_string());
+            }
+            ["retry", "max_attempts"] => self.config.retry.max_attempts = value.parse()?,
+            ["retry", "initial_delay_ms"] => self.config.retry.initial_delay_ms = value.parse()?,
+            ["retry", "max_delay_ms"] => self.config.retry.max_delay_ms = value.parse()?,
+            ["retry", "jitter"] => self.config.retry.jitter = value.parse()?,
+            ["budget", "daily"] => self.config.budget.limits.daily = Some(value.parse()?),
+            ["budget", "monthly"] => self.config.budget.limits.monthly = Some(value.parse()?),
+            ["budget", "on_exceed"] => self.config.budget.on_exceed = value.parse()?,
+            ["budget", "providers", provider, "daily"] => {
+                self.budget_limits_mut(provider).daily = Some(value.parse()?)
+            }
+            ["budget", "providers", provider, "monthly"] => {
+                self.budget_limits_mut(provider).monthly = Some(value.parse()?)
+            }
             _ => anyhow::bail!("Unknown config key: {}", key),
         }
 
@@ -208,52 +535,120 @@ impl ConfigManager {
         Ok(())
     }
 
+    fn provider_config_mut(&mut self, provider: &str) -> Result<&mut ProviderConfig> {
+        self.config
+            .api
+            .providers
+            .get_mut(provider)
+            .ok_or_else(|| anyhow::anyhow!("Unknown provider: {}", provider))
+    }
+
+    fn set_http(http: &mut HttpConfig, key: &str, value: &str) -> Result<()> {
+        match key {
+            "connect_timeout_secs" => http.connect_timeout_secs = Some(value.parse()?),
+            "read_timeout_secs" => http.read_timeout_secs = Some(value.parse()?),
+            "timeout_secs" => http.timeout_secs = Some(value.parse()?),
+            "proxy" => http.proxy = Some(value.to_string()),
+            "ca_bundle" => http.ca_bundle = Some(PathBuf::from(value)),
+            _ => anyhow::bail!("Unknown http setting: {}", key),
+        }
+        Ok(())
+    }
+
+    fn budget_limits_mut(&mut self, provider: &str) -> &mut BudgetLimits {
+        self.config
+            .budget
+            .providers
+            .entry(provider.to_string())
+            .or_default()
+    }
+
     pub fn save(&self) -> Result<()> {
         let content = toml::to_string_pretty(&self.config)?;
         std::fs::write(&self.config_path, content)?;
         Ok(())
     }
 
+    pub fn get_provider_config(&self, provider: &str) -> Result<&ProviderConfig> {
+        self.config.api.providers.get(provider).ok_or_else(|| {
+            LlmCliError::ConfigError(format!("Unknown provider: {}", provider)).into()
+        })
+    }
+
+    /// Resolves the API key for a provider.
+    ///
+    /// Providers with neither `api_key` nor `api_key_env` configured are treated
+    /// as keyless and get an empty key.
     pub fn get_api_key(&self, provider: &str) -> Result<String> {
-        let provider_config = match provider {
-            "openai" => &self.config.api.providers.openai,
-            "anthropic" => &self.config.api.providers.anthropic,
-            "google" => &self.config.api.providers.google,
-            _ => anyhow::bail!("Unknown provider: {}", provider),
-        };
+        let provider_config = self.get_provider_config(provider)?;
 
         if let Some(key) = &provider_config.api_key {
             return Ok(key.clone());
         }
 
-        std::env::var(&provider_config.api_key_env).map_err(|_| {
-            anyhow::anyhow!(
+        let Some(api_key_env) = &provider_config.api_key_env else {
+            return Ok(String::new());
+        };
+
+        std::env::var(api_key_env).map_err(|_| {
+            LlmCliError::ConfigError(format!(
                 "API key not found for {}. Set {} or configure api.providers.{}.api_key",
-                provider,
-                provider_config.api_key_env,
-                provider
-            )
+                provider, api_key_env, provider
+            ))
+            .into()
         })
     }
 
+    /// Looks a model up by name or, failing that, by one of its aliases.
     pub fn get_model_info(&self, model_name: &str) -> Option<&ModelInfo> {
-        // Access self.config first, then .models
-        self.config
-            .models
-            .available
+        let available = &self.config.models.available;
+        available.iter().find(|m| m.name == model_name).or_else(|| {
+            available
+                .iter()
+                .find(|m| m.aliases.iter().any(|alias| alias == model_name))
+        })
+    }
+
+    /// The models a name stands for, in the order to try them: the chain
+    /// under `models.fallbacks` if the name is an alias, else the model itself.
+    pub fn resolve_models(&self, name: &str) -> Result<Vec<&ModelInfo>> {
+        let Some(chain) = self.config.models.fallbacks.get(name) else {
+            let model = self.get_model_info(name).ok_or_else(|| {
+                LlmCliError::ConfigError(format!("Model '{}' not found in config.toml", name))
+            })?;
+            return Ok(vec![model]);
+        };
+
+        if chain.is_empty() {
+            return Err(
+                LlmCliError::ConfigError(format!("Fallback chain '{}' is empty", name)).into(),
+            );
+        }
+        chain
             .iter()
-            .find(|m| m.name == model_name)
+            .map(|model| {
+                self.get_model_info(model).ok_or_else(|| {
+                    LlmCliError::ConfigError(format!(
+                        "Fallback chain '{}' lists unknown model '{}'",
+                        name, model
+                    ))
+                    .into()
+                })
+            })
+            .collect()
     }
+
     pub fn get_available_models(&self) -> Vec<&ModelInfo> {
         self.config
             .models
             .available
             .iter()
-            .filter(|m| match m.provider.as_str() {
-                "openai" => self.config.api.providers.openai.enabled,
-                "anthropic" => self.config.api.providers.anthropic.enabled,
-                "google" => self.config.api.providers.google.enabled,
-                _ => false,
+            .filter(|m| {
+                self.config
+                    .api
+                    .providers
+                    .get(&m.provider)
+                    .is_some_and(|p| p.enabled)
             })
             .collect()
     }
@@ -5,10 +5,12 @@ mod config;
 mod output;
 mod session;
 mod template;
+mod usage;
 mod utils;
 
 use clap::Parser;
 use cli::{Cli, Commands};
+use utils::LlmCliError;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -21,19 +23,34 @@ async fn main() -> anyhow::Result<()> {
     // Parse CLI arguments
     let cli = Cli::parse();
 
+    // Typed errors get their own exit codes so scripts can react to them
+    let result = run(cli).await;
+    if let Some(error) = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<LlmCliError>())
+    {
+        eprintln!("Error: {}", error);
+        if let Some(hint) = error.hint() {
+            eprintln!("Hint: {}", hint);
+        }
+        std::process::exit(error.exit_code());
+    }
+    result
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
     // Route to appropriate command handler
     match cli.command {
-        Commands::Ask {
-            query,
-            file,
-            output,
+        Commands::Ask(args) => {
+            commands::ask::execute(args).await?;
+        }
+        Commands::Chat {
+            session,
             model,
-            template,
+            system,
         } => {
-            commands::ask::execute(query, file, output, model, template).await?;
-        }
-        Commands::Chat { session, model } => {
-            commands::chat::execute(session, model).await?;
+            commands::chat::execute(session, model, system).await?;
         }
         Commands::Config { action } => {
             commands::config::execute(action)?;
@@ -48,6 +65,9 @@ async fn main() -> anyhow::Result<()> {
             // You'll need to add 'pub mod compare' to src/commands/mod.rs first
             commands::compare::execute(query, models).await?;
         }
+        Commands::Usage { by, since, until } => {
+            commands::usage::execute(by, since, until)?;
+        }
     }
 
     Ok(())
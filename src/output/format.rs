@@ -0,0 +1,55 @@
+use crate::api::{FinishReason, Usage};
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How `ask` presents a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable output for the terminal
+    #[default]
+    Text,
+    /// A machine-readable envelope with the response and its metadata
+    Json,
+    /// A Markdown document with the prompt and the response
+    Markdown,
+    /// The response text only, with no decoration
+    Raw,
+}
+
+/// Everything known about a completed response, as emitted by `--format json`.
+#[derive(Debug, Serialize)]
+pub struct ResponseEnvelope {
+    pub id: String,
+    pub model: String,
+    pub provider: String,
+    pub text: String,
+    pub latency_ms: u128,
+    pub usage: Option<Usage>,
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl ResponseEnvelope {
+    pub fn render(&self, format: OutputFormat, prompt: &str) -> Result<String> {
+        let rendered = match format {
+            OutputFormat::Text | OutputFormat::Raw => self.text.clone(),
+            OutputFormat::Json => serde_json::to_string_pretty(self)?,
+            OutputFormat::Markdown => {
+                let quoted = prompt
+                    .lines()
+                    .map(|line| format!("> {}", line).trim_end().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    "## Prompt\n\n{}\n\n## Response ({}, {})\n\n{}\n",
+                    quoted,
+                    self.model,
+                    self.provider,
+                    self.text.trim_end()
+                )
+            }
+        };
+
+        Ok(rendered)
+    }
+}
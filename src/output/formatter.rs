@@ -1,4 +1,7 @@
+use super::markdown::MarkdownRenderer;
+use crate::api::{ChatResponse, FinishReason};
 use colored::*;
+use std::io::{IsTerminal, Write};
 
 pub struct OutputFormatter {
     syntax_highlighting: bool,
@@ -12,25 +15,111 @@ impl OutputFormatter {
             markdown_rendering,
         }
     }
-    
+
+    /// Markdown is only rendered for a terminal; piped output stays verbatim.
+    fn renders_markdown(&self) -> bool {
+        self.markdown_rendering && std::io::stdout().is_terminal()
+    }
+
     pub fn print_response(&self, text: &str) {
-        if self.markdown_rendering {
-            // TODO: Implement markdown rendering
-            println!("{}", text);
+        if text.is_empty() {
+            println!("(Received empty response from model)");
+        } else if self.renders_markdown() {
+            print!(
+                "{}",
+                MarkdownRenderer::render(text, self.syntax_highlighting)
+            );
         } else {
             println!("{}", text);
         }
     }
-    
+
+    /// Starts printing a streamed response, rendering it line by line as tokens arrive.
+    pub fn stream(&self) -> ResponseStream {
+        ResponseStream {
+            renderer: self
+                .renders_markdown()
+                .then(|| MarkdownRenderer::new(self.syntax_highlighting)),
+            received_any: false,
+        }
+    }
+
+    /// Prints the token usage and finish reason of a response.
+    pub fn print_usage(&self, response: &ChatResponse) {
+        let usage = response
+            .usage
+            .map_or("not reported".to_string(), |usage| usage.to_string());
+        let finish_reason = response
+            .finish_reason
+            .as_ref()
+            .map_or("unknown".to_string(), |reason| reason.to_string());
+        eprintln!(
+            "{}",
+            format!("tokens: {} · finish: {}", usage, finish_reason).bright_black()
+        );
+    }
+
+    /// Warns when a response was cut off by the token limit.
+    pub fn warn_if_truncated(&self, response: &ChatResponse) {
+        if response.finish_reason == Some(FinishReason::Length) {
+            eprintln!(
+                "{} response truncated at the max_tokens limit",
+                "Warning:".yellow().bold()
+            );
+        }
+    }
+
+    /// Says so when a fallback chain had to answer with a later model.
+    pub fn note_fallback(&self, requested: &str, response: &ChatResponse) {
+        if !response.model.is_empty() && response.model != requested {
+            eprintln!(
+                "{} answered by fallback model {} ({} failed)",
+                "Note:".yellow().bold(),
+                response.model,
+                requested
+            );
+        }
+    }
+
     pub fn print_error(&self, error: &str) {
         eprintln!("{} {}", "Error:".red().bold(), error);
     }
-    
+
+    pub fn print_hint(&self, hint: &str) {
+        eprintln!("{} {}", "Hint:".yellow().bold(), hint);
+    }
+
     pub fn print_success(&self, message: &str) {
         println!("{} {}", "✓".green(), message);
     }
-    
+
     pub fn print_info(&self, message: &str) {
         println!("{} {}", "→".blue(), message);
     }
-}
\ No newline at end of file
+}
+
+/// Prints a response that arrives in pieces.
+pub struct ResponseStream {
+    renderer: Option<MarkdownRenderer>,
+    received_any: bool,
+}
+
+impl ResponseStream {
+    pub fn write(&mut self, token: &str) -> std::io::Result<()> {
+        self.received_any |= !token.is_empty();
+        match &mut self.renderer {
+            Some(renderer) => print!("{}", renderer.push(token)),
+            None => print!("{}", token),
+        }
+        std::io::stdout().flush()
+    }
+
+    /// Prints whatever is still buffered and ends the response.
+    pub fn finish(mut self) {
+        match &mut self.renderer {
+            _ if !self.received_any => println!("(Received empty response from model)"),
+            Some(renderer) => print!("{}", renderer.finish()),
+            None => println!(),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use colored::*;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::as_24_bit_terminal_escaped;
+
+const THEME: &str = "base16-ocean.dark";
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    &THEMES.get_or_init(ThemeSet::load_defaults).themes[THEME]
+}
+
+struct CodeBlock {
+    fence: String,
+    highlighter: Option<HighlightLines<'static>>,
+}
+
+/// Renders Markdown for the terminal one line at a time.
+///
+/// Text can arrive in arbitrary pieces (as it does when streaming); each
+/// line is rendered as soon as it is complete. Tables are held back until
+/// their last row so the columns can be aligned.
+pub struct MarkdownRenderer {
+    syntax_highlighting: bool,
+    pending: String,
+    code: Option<CodeBlock>,
+    table: Vec<String>,
+}
+
+impl MarkdownRenderer {
+    pub fn new(syntax_highlighting: bool) -> Self {
+        Self {
+            syntax_highlighting,
+            pending: String::new(),
+            code: None,
+            table: Vec::new(),
+        }
+    }
+
+    pub fn render(text: &str, syntax_highlighting: bool) -> String {
+        let mut renderer = Self::new(syntax_highlighting);
+        let mut out = renderer.push(text);
+        out.push_str(&renderer.finish());
+        out
+    }
+
+    /// Feeds more text, returning the rendering of any lines it completed.
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.pending.push_str(chunk);
+
+        let mut out = String::new();
+        while let Some(pos) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=pos).collect();
+            out.push_str(&self.render_line(line.trim_end_matches(['\n', '\r'])));
+        }
+        out
+    }
+
+    /// Renders whatever is still buffered at the end of the text.
+    pub fn finish(&mut self) -> String {
+        let mut out = String::new();
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            out.push_str(&self.render_line(&line));
+        }
+        out.push_str(&self.flush_table());
+        self.code = None;
+        out
+    }
+
+    fn render_line(&mut self, line: &str) -> String {
+        let trimmed = line.trim_start();
+
+        if let Some(code) = &mut self.code {
+            if trimmed.starts_with(code.fence.as_str()) && trimmed.trim_end() == code.fence {
+                self.code = None;
+                return format!("{}\n", line.dimmed());
+            }
+            return match &mut code.highlighter {
+                Some(highlighter) => highlight(highlighter, line),
+                None => format!("{}\n", line),
+            };
+        }
+
+        if trimmed.starts_with('|') {
+            self.table.push(trimmed.to_string());
+            return String::new();
+        }
+
+        let mut out = self.flush_table();
+
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            let lang = trimmed.trim_start_matches(fence).trim();
+            self.code = Some(CodeBlock {
+                fence: fence.to_string(),
+                highlighter: self.highlighter_for(lang),
+            });
+            out.push_str(&format!("{}\n", line.dimmed()));
+            return out;
+        }
+
+        out.push_str(&render_block_line(line));
+        out.push('\n');
+        out
+    }
+
+    fn highlighter_for(&self, lang: &str) -> Option<HighlightLines<'static>> {
+        if !self.syntax_highlighting || lang.is_empty() {
+            return None;
+        }
+        let syntax = syntaxes().find_syntax_by_token(lang)?;
+        Some(HighlightLines::new(syntax, theme()))
+    }
+
+    fn flush_table(&mut self) -> String {
+        if self.table.is_empty() {
+            return String::new();
+        }
+
+        let rows: Vec<Vec<String>> = std::mem::take(&mut self.table)
+            .iter()
+            .filter(|row| !is_separator_row(row))
+            .map(|row| split_row(row).iter().map(|c| render_inline(c)).collect())
+            .collect();
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| {
+                rows.iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| visible_width(cell))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let format_row = |row: &Vec<String>, bold: bool| {
+            let cells: Vec<String> = (0..columns)
+                .map(|i| {
+                    let cell = row.get(i).map(String::as_str).unwrap_or("");
+                    let padding = " ".repeat(widths[i] - visible_width(cell));
+                    if bold {
+                        format!("{}{}", cell.bold(), padding)
+                    } else {
+                        format!("{}{}", cell, padding)
+                    }
+                })
+                .collect();
+            format!("{}\n", cells.join(&format!(" {} ", "│".dimmed())))
+        };
+
+        let mut out = String::new();
+        for (i, row) in rows.iter().enumerate() {
+            out.push_str(&format_row(row, i == 0));
+            if i == 0 && rows.len() > 1 {
+                let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+                out.push_str(&format!("{}\n", rule.join("─┼─").dimmed()));
+            }
+        }
+        out
+    }
+}
+
+fn highlight(highlighter: &mut HighlightLines<'static>, line: &str) -> String {
+    let with_newline = format!("{}\n", line);
+    match highlighter.highlight_line(&with_newline, syntaxes()) {
+        Ok(ranges) => {
+            let escaped = as_24_bit_terminal_escaped(&ranges, false);
+            format!("{}\x1b[0m\n", escaped.trim_end_matches('\n'))
+        }
+        Err(_) => with_newline,
+    }
+}
+
+fn is_separator_row(row: &str) -> bool {
+    split_row(row).iter().all(|cell| {
+        let cell = cell.trim();
+        !cell.is_empty() && cell.chars().all(|c| matches!(c, '-' | ':'))
+    })
+}
+
+fn split_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = row.strip_suffix('|').unwrap_or(row);
+    row.split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Width of a string as displayed, ignoring ANSI escape sequences.
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        match (in_escape, c) {
+            (false, '\x1b') => in_escape = true,
+            (true, 'm') => in_escape = false,
+            (true, _) => {}
+            (false, _) => width += 1,
+        }
+    }
+    width
+}
+
+fn render_block_line(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+        let heading = render_inline(trimmed[level..].trim());
+        return match level {
+            1 => heading.bold().underline().bright_cyan().to_string(),
+            2 => heading.bold().bright_cyan().to_string(),
+            _ => heading.bold().cyan().to_string(),
+        };
+    }
+
+    let rule_char = trimmed.chars().next().unwrap_or(' ');
+    if matches!(rule_char, '-' | '*' | '_')
+        && trimmed.len() >= 3
+        && trimmed.chars().all(|c| c == rule_char || c == ' ')
+    {
+        return "─".repeat(40).dimmed().to_string();
+    }
+
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return format!(
+            "{}{} {}",
+            indent,
+            "│".dimmed(),
+            render_inline(quote.trim_start()).italic()
+        );
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            let item = match item.strip_prefix("[ ] ") {
+                Some(rest) => format!("☐ {}", render_inline(rest)),
+                None => match item.strip_prefix("[x] ") {
+                    Some(rest) => format!("☑ {}", render_inline(rest)),
+                    None => render_inline(item),
+                },
+            };
+            return format!("{}{} {}", indent, "•".cyan(), item);
+        }
+    }
+
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && trimmed[digits..].starts_with(". ") {
+        return format!(
+            "{}{} {}",
+            indent,
+            trimmed[..=digits].cyan(),
+            render_inline(&trimmed[digits + 2..])
+        );
+    }
+
+    format!("{}{}", indent, render_inline(trimmed))
+}
+
+/// Renders inline spans: `code`, **bold**, *italic*, and [links](url).
+fn render_inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                out.push_str(&rest[1..=end].yellow().to_string());
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+
+        for marker in ["**", "__"] {
+            if let Some(inner) = rest.strip_prefix(marker) {
+                if let Some(end) = inner.find(marker).filter(|&end| end > 0) {
+                    out.push_str(&render_inline(&inner[..end]).bold().to_string());
+                    rest = &inner[end + marker.len()..];
+                    break;
+                }
+            }
+        }
+        if !rest.starts_with(c) {
+            continue;
+        }
+
+        // A single `_` inside a word (snake_case) is not emphasis
+        let word_start = !out.chars().last().is_some_and(char::is_alphanumeric);
+        if (c == '*' || (c == '_' && word_start)) && !rest[1..].starts_with(c) {
+            if let Some(end) = rest[1..]
+                .find(c)
+                .filter(|&end| end > 0 && !rest[1..].starts_with(' '))
+            {
+                out.push_str(&render_inline(&rest[1..=end]).italic().to_string());
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+
+        if c == '[' {
+            if let Some((label, url, len)) = parse_link(rest) {
+                out.push_str(&format!(
+                    "{} {}",
+                    render_inline(label).underline().blue(),
+                    format!("({})", url).dimmed()
+                ));
+                rest = &rest[len..];
+                continue;
+            }
+        }
+
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    out
+}
+
+/// Parses `[label](url)` at the start of `text`, returning the parts and
+/// the length consumed.
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let url_end = text[label_end + 2..].find(')')? + label_end + 2;
+    let label = &text[1..label_end];
+    if label.contains('[') {
+        return None;
+    }
+    Some((label, &text[label_end + 2..url_end], url_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_ansi(text: &str) -> String {
+        let mut out = String::new();
+        let mut in_escape = false;
+        for c in text.chars() {
+            match (in_escape, c) {
+                (false, '\x1b') => in_escape = true,
+                (true, 'm') => in_escape = false,
+                (true, _) => {}
+                (false, _) => out.push(c),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_blocks_and_inline_spans() {
+        let text = "# Title\n- **bold** and *it* with `code` in snake_case_name\n1. [docs](https://x.y)\n> quoted\n";
+        assert_eq!(
+            strip_ansi(&MarkdownRenderer::render(text, false)),
+            "Title\n• bold and it with code in snake_case_name\n1. docs (https://x.y)\n│ quoted\n"
+        );
+    }
+
+    #[test]
+    fn test_tables_are_aligned() {
+        let text = "| Name | Age |\n|---|---:|\n| Alexandra | 7 |\n| Bo | 42 |\nafter";
+        assert_eq!(
+            strip_ansi(&MarkdownRenderer::render(text, false)),
+            "Name      │ Age\n──────────┼────\nAlexandra │ 7  \nBo        │ 42 \nafter\n"
+        );
+    }
+
+    #[test]
+    fn test_streamed_chunks_render_per_line() {
+        let mut renderer = MarkdownRenderer::new(true);
+
+        assert_eq!(renderer.push("```ru"), "");
+        assert_eq!(strip_ansi(&renderer.push("st\nlet x = 1;")), "```rust\n");
+        let code = renderer.push("\n```\n**done**");
+        assert!(code.contains("\x1b[38;2;"), "code should be highlighted");
+        assert_eq!(strip_ansi(&code), "let x = 1;\n```\n");
+        assert_eq!(strip_ansi(&renderer.finish()), "done\n");
+    }
+}
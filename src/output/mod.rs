@@ -1,3 +1,6 @@
+mod format;
 mod formatter;
+mod markdown;
 
-pub use formatter::OutputFormatter;
\ No newline at end of file
+pub use format::{OutputFormat, ResponseEnvelope};
+pub use formatter::OutputFormatter;
@@ -0,0 +1,588 @@
+use super::{Session, SessionMessage, SessionNode};
+use crate::utils::LlmCliError;
+use anyhow::Result;
+use clap::ValueEnum;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+/// File formats for `session export` and `session import`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SessionFormat {
+    /// The stored session, with every branch
+    #[default]
+    Json,
+    /// A Markdown transcript of the active branch
+    Markdown,
+    /// A standalone HTML page with the active branch
+    Html,
+    /// One message per line, as JSON
+    Jsonl,
+    /// The active branch as an OpenAI `messages` array
+    Openai,
+}
+
+impl Session {
+    /// Renders the session as `format`. Only `Json` keeps the branches off
+    /// the active one; the others hold the conversation as it reads now.
+    pub fn export(&self, format: SessionFormat) -> Result<String> {
+        let messages = self.messages();
+        let rendered = match format {
+            SessionFormat::Json => serde_json::to_string_pretty(self)?,
+            SessionFormat::Markdown => {
+                // The comment before each heading marks where a message starts,
+                // so headings inside replies are not mistaken for one on import
+                let mut out = format!("# {}\n", self.name);
+                for message in &messages {
+                    let model = message
+                        .model
+                        .as_ref()
+                        .map(|model| format!(" model=\"{}\"", model))
+                        .unwrap_or_default();
+                    out.push_str(&format!(
+                        "\n<!-- message role=\"{}\"{} timestamp=\"{}\" -->\n## {}\n\n{}\n",
+                        message.role,
+                        model,
+                        message.timestamp,
+                        heading(message),
+                        message.content.trim_end()
+                    ));
+                }
+                out
+            }
+            SessionFormat::Html => {
+                let mut out = format!(
+                    "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n<style>\n{style}\n</style>\n</head>\n<body>\n<h1>{name}</h1>\n",
+                    name = escape_html(&self.name),
+                    style = HTML_STYLE
+                );
+                for message in &messages {
+                    out.push_str(&format!(
+                        "<section class=\"message {}\" data-timestamp=\"{}\"{}>\n<h2>{}</h2>\n<pre>{}</pre>\n</section>\n",
+                        escape_html(&message.role),
+                        message.timestamp,
+                        message
+                            .model
+                            .as_ref()
+                            .map(|model| format!(" data-model=\"{}\"", escape_html(model)))
+                            .unwrap_or_default(),
+                        escape_html(&heading(message)),
+                        escape_html(message.content.trim_end())
+                    ));
+                }
+                out.push_str("</body>\n</html>\n");
+                out
+            }
+            SessionFormat::Jsonl => messages
+                .iter()
+                .map(|message| Ok(serde_json::to_string(message)? + "\n"))
+                .collect::<Result<String>>()?,
+            SessionFormat::Openai => {
+                let messages: Vec<Value> = messages
+                    .iter()
+                    .map(|m| json!({ "role": m.role, "content": m.content }))
+                    .collect();
+                serde_json::to_string_pretty(&messages)?
+            }
+        };
+        Ok(rendered)
+    }
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 48rem; margin: 2rem auto; }
+.message { border-left: 4px solid #ccc; padding: 0 1rem; margin: 1.5rem 0; }
+.message.user { border-color: #4a90d9; }
+.message.assistant { border-color: #5cb85c; }
+h2 { font-size: 1rem; }
+pre { white-space: pre-wrap; font-family: inherit; }";
+
+/// "User", or "Assistant (model)" when the model is known.
+fn heading(message: &SessionMessage) -> String {
+    let mut role = message.role.clone();
+    if let Some(first) = role.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    match &message.model {
+        Some(model) => format!("{} ({})", role, model),
+        None => role,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+}
+
+/// Reads the sessions in `path`: anything `session export` writes, an OpenAI
+/// `messages` array, or a ChatGPT or Claude data export (the `.zip` archive
+/// or its `conversations.json`), which may hold many conversations. The
+/// format is told from the extension and the content.
+pub fn import(path: &Path) -> Result<Vec<Session>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(slug)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "imported".to_string());
+    let unrecognized =
+        |why: &str| LlmCliError::InvalidInput(format!("Cannot import {}: {}", path.display(), why));
+
+    let text = if extension == "zip" {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+        let mut conversations = archive
+            .by_name("conversations.json")
+            .map_err(|_| unrecognized("the archive has no conversations.json"))?;
+        let mut text = String::new();
+        conversations.read_to_string(&mut text)?;
+        text
+    } else {
+        std::fs::read_to_string(path)?
+    };
+
+    let sessions = match extension.as_str() {
+        "md" | "markdown" => from_markdown(&text, &stem).map(|s| vec![s]),
+        "html" | "htm" => from_html(&text, &stem).map(|s| vec![s]),
+        "jsonl" => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .ok()
+                    .and_then(|v| openai_message(&v))
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|messages| vec![Session::from_messages(&stem, messages)]),
+        _ => serde_json::from_str(&text)
+            .ok()
+            .and_then(|value| from_json(value, &stem)),
+    };
+    if let Some(broken) = sessions.iter().flatten().find(|s| !s.is_well_formed()) {
+        let why = format!("session '{}' has messages out of order", broken.name);
+        return Err(unrecognized(&why).into());
+    }
+    match sessions {
+        Some(sessions) if sessions.iter().any(|s| !s.nodes.is_empty()) => Ok(sessions
+            .into_iter()
+            .filter(|s| !s.nodes.is_empty())
+            .enumerate()
+            .map(|(i, mut session)| {
+                // Untitled conversations are named after the file
+                if session.name.is_empty() {
+                    session.name = format!("{}-{}", stem, i + 1);
+                }
+                session
+            })
+            .collect()),
+        _ => Err(unrecognized("no messages found in a known format").into()),
+    }
+}
+
+fn from_json(value: Value, stem: &str) -> Option<Vec<Session>> {
+    if value.get("nodes").is_some() {
+        return Some(vec![serde_json::from_value(value).ok()?]);
+    }
+    // `{"messages": [...]}`: a chat request, or a session exported before
+    // sessions became trees
+    if let Some(messages) = value.get("messages").and_then(|m| m.as_array()) {
+        let name = value.get("name").and_then(|n| n.as_str()).unwrap_or(stem);
+        let messages = messages.iter().map(openai_message).collect::<Option<_>>()?;
+        return Some(vec![Session::from_messages(name, messages)]);
+    }
+
+    let items = match value {
+        Value::Array(items) => items,
+        single => vec![single],
+    };
+    let first = items.first()?;
+    if first.get("mapping").is_some() {
+        items.iter().map(chatgpt_session).collect()
+    } else if first.get("chat_messages").is_some() {
+        items.iter().map(claude_session).collect()
+    } else {
+        let messages = items.iter().map(openai_message).collect::<Option<_>>()?;
+        Some(vec![Session::from_messages(stem, messages)])
+    }
+}
+
+/// A message as `{"role", "content"}`, where the content may be OpenAI's list
+/// of parts; our own JSONL lines carry the remaining fields too.
+fn openai_message(value: &Value) -> Option<SessionMessage> {
+    if let Ok(message) = serde_json::from_value::<SessionMessage>(value.clone()) {
+        return Some(message);
+    }
+    Some(message(
+        value.get("role")?.as_str()?,
+        text_of(value.get("content")?)?,
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+/// Text from a string or a list of `{"type": "text", "text"}` parts.
+fn text_of(content: &Value) -> Option<String> {
+    match content {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter_map(|part| match part {
+                    Value::String(text) => Some(text.as_str()),
+                    part => part.get("text")?.as_str(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => None,
+    }
+}
+
+fn message(role: &str, content: String, timestamp: i64) -> SessionMessage {
+    SessionMessage {
+        timestamp,
+        ..SessionMessage::new(role, content)
+    }
+}
+
+/// A conversation from ChatGPT's `conversations.json`. Its `mapping` is
+/// already a tree, so every regenerated reply and edited question carries
+/// over as a branch; hidden system and tool messages are dropped.
+fn chatgpt_session(conversation: &Value) -> Option<Session> {
+    let mapping = conversation.get("mapping")?.as_object()?;
+    let title = conversation
+        .get("title")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default();
+    let mut session = Session::new(&slug(title));
+    session.title = Some(title.to_string()).filter(|t| !t.is_empty());
+    if let Some(created) = conversation.get("create_time").and_then(|t| t.as_f64()) {
+        session.created_at = created as i64;
+    }
+    if let Some(updated) = conversation.get("update_time").and_then(|t| t.as_f64()) {
+        session.updated_at = updated as i64;
+    }
+
+    let mut stack: Vec<(&str, Option<usize>)> = mapping
+        .iter()
+        .filter(|(_, entry)| {
+            let parent = entry.get("parent").and_then(|p| p.as_str());
+            parent.is_none_or(|parent| !mapping.contains_key(parent))
+        })
+        .map(|(key, _)| (key.as_str(), None))
+        .collect();
+    // Where each mapping entry ended up: its own node, or its nearest kept
+    // ancestor when it was dropped
+    let mut placed: HashMap<&str, Option<usize>> = HashMap::new();
+    while let Some((key, parent)) = stack.pop() {
+        if placed.contains_key(key) {
+            continue;
+        }
+        let entry = &mapping[key];
+        let mut id = parent;
+        if let Some(message) = entry.get("message").and_then(chatgpt_message) {
+            session.nodes.push(SessionNode { parent, message });
+            id = Some(session.nodes.len() - 1);
+        }
+        placed.insert(key, id);
+
+        let children = entry.get("children").and_then(|c| c.as_array());
+        for child in children.into_iter().flatten().rev() {
+            if let Some(child) = child.as_str().filter(|c| mapping.contains_key(*c)) {
+                stack.push((child, id));
+            }
+        }
+    }
+
+    session.head = conversation
+        .get("current_node")
+        .and_then(|c| c.as_str())
+        .and_then(|current| placed.get(current).copied().flatten())
+        .or(session.nodes.len().checked_sub(1));
+    if session.title.is_none() {
+        session.title = session.auto_title();
+    }
+    Some(session)
+}
+
+fn chatgpt_message(value: &Value) -> Option<SessionMessage> {
+    let role = value.get("author")?.get("role")?.as_str()?;
+    if role != "user" && role != "assistant" {
+        return None;
+    }
+    let content = text_of(value.get("content")?.get("parts")?)?;
+    if content.trim().is_empty() {
+        return None;
+    }
+    let timestamp = value
+        .get("create_time")
+        .and_then(|t| t.as_f64())
+        .unwrap_or_default();
+    let mut message = message(role, content, timestamp as i64);
+    message.model = value
+        .get("metadata")
+        .and_then(|m| m.get("model_slug"))
+        .and_then(|m| m.as_str())
+        .map(str::to_string);
+    Some(message)
+}
+
+/// A conversation from Claude's `conversations.json`, a flat list of
+/// `human` and `assistant` messages.
+fn claude_session(conversation: &Value) -> Option<Session> {
+    let time = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(|t| t.as_str())
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp())
+    };
+
+    let messages = conversation
+        .get("chat_messages")?
+        .as_array()?
+        .iter()
+        .filter_map(|value| {
+            let role = match value.get("sender")?.as_str()? {
+                "human" => "user",
+                other => other,
+            };
+            let content = match value.get("text").and_then(|t| t.as_str()) {
+                Some(text) if !text.is_empty() => text.to_string(),
+                _ => text_of(value.get("content")?)?,
+            };
+            let timestamp = time(value, "created_at").unwrap_or_default();
+            Some(message(role, content, timestamp))
+        })
+        .collect();
+
+    let name = conversation
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or_default();
+    let mut session = Session::from_messages(&slug(name), messages);
+    if !name.is_empty() {
+        session.title = Some(name.to_string());
+    }
+    if let Some(created) = time(conversation, "created_at") {
+        session.created_at = created;
+    }
+    if let Some(updated) = time(conversation, "updated_at") {
+        session.updated_at = updated;
+    }
+    Some(session)
+}
+
+/// Reads back a transcript written by `export --format markdown`. Files
+/// without its message markers, such as hand-written ones, are split at
+/// `## User`, `## Assistant` and `## System` headings instead.
+fn from_markdown(text: &str, stem: &str) -> Option<Session> {
+    let marker = Regex::new(
+        r#"^<!-- message role="(user|assistant|system)"(?: model="([^"]*)")? timestamp="(-?\d+)" -->$"#,
+    )
+    .ok()?;
+    let heading = Regex::new(r"^## (User|Assistant|System)(?: \((.+)\))?$").ok()?;
+    let marked = text.lines().any(|line| marker.is_match(line));
+    let now = chrono::Utc::now().timestamp();
+    let mut name = stem.to_string();
+    let mut messages: Vec<SessionMessage> = Vec::new();
+    let mut after_marker = false;
+    for line in text.lines() {
+        let heading_line = std::mem::take(&mut after_marker) && line.starts_with("## ");
+        if let Some(title) = line.strip_prefix("# ").filter(|_| messages.is_empty()) {
+            name = title.trim().to_string();
+        } else if let Some(caps) = marker.captures(line) {
+            let mut message = message(&caps[1], String::new(), caps[3].parse().unwrap_or(now));
+            message.model = caps.get(2).map(|m| m.as_str().to_string());
+            messages.push(message);
+            after_marker = true;
+        } else if heading_line {
+            continue;
+        } else if let Some(caps) = heading.captures(line).filter(|_| !marked) {
+            let mut message = message(&caps[1].to_lowercase(), String::new(), now);
+            message.model = caps.get(2).map(|m| m.as_str().to_string());
+            messages.push(message);
+        } else if let Some(message) = messages.last_mut() {
+            message.content.push_str(line);
+            message.content.push('\n');
+        }
+    }
+    for message in &mut messages {
+        message.content = message.content.trim().to_string();
+    }
+    Some(Session::from_messages(&name, messages))
+}
+
+/// Reads back a page written by `export --format html`.
+fn from_html(text: &str, stem: &str) -> Option<Session> {
+    let section = Regex::new(
+        r#"(?s)<section class="message ([^"]+)" data-timestamp="(-?\d+)"(?: data-model="([^"]*)")?>\s*<h2>[^<]*</h2>\s*<pre>(.*?)</pre>"#,
+    )
+    .ok()?;
+    let name = Regex::new(r"(?s)<title>(.*?)</title>")
+        .ok()?
+        .captures(text)
+        .map(|caps| unescape_html(&caps[1]))
+        .unwrap_or_else(|| stem.to_string());
+    let messages = section
+        .captures_iter(text)
+        .map(|caps| {
+            let mut message = message(
+                &unescape_html(&caps[1]),
+                unescape_html(&caps[4]),
+                caps[2].parse().unwrap_or_default(),
+            );
+            message.model = caps.get(3).map(|m| unescape_html(m.as_str()));
+            message
+        })
+        .collect();
+    Some(Session::from_messages(&name, messages))
+}
+
+/// A name usable as `--session` from a conversation title:
+/// "Rust lifetimes?" becomes "rust-lifetimes".
+fn slug(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Session {
+        let mut reply = message("assistant", "Use <T: Ord> & friends".to_string(), 2);
+        reply.model = Some("llama3".to_string());
+        Session::from_messages(
+            "work",
+            vec![message("user", "How do I sort?".to_string(), 1), reply],
+        )
+    }
+
+    fn roundtrip(format: SessionFormat, extension: &str) -> Session {
+        let dir = std::env::temp_dir().join(format!("llm-cli-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("work.{}", extension));
+        std::fs::write(&path, sample().export(format).unwrap()).unwrap();
+        let mut sessions = import(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sessions.len(), 1);
+        sessions.remove(0)
+    }
+
+    #[test]
+    fn test_export_formats_roundtrip() {
+        for (format, extension) in [
+            (SessionFormat::Json, "json"),
+            (SessionFormat::Markdown, "md"),
+            (SessionFormat::Html, "html"),
+            (SessionFormat::Jsonl, "jsonl"),
+            (SessionFormat::Openai, "json"),
+        ] {
+            let session = roundtrip(format, extension);
+            let messages = session.messages();
+            assert_eq!(messages.len(), 2, "{:?}", format);
+            assert_eq!(messages[0].role, "user");
+            assert_eq!(
+                messages[1].content, "Use <T: Ord> & friends",
+                "{:?}",
+                format
+            );
+            if format != SessionFormat::Openai {
+                assert_eq!(session.name, "work");
+                assert_eq!(messages[1].model.as_deref(), Some("llama3"));
+            }
+        }
+
+        let markdown = sample().export(SessionFormat::Markdown).unwrap();
+        assert_eq!(
+            markdown,
+            "# work\n\n<!-- message role=\"user\" timestamp=\"1\" -->\n## User\n\nHow do I sort?\n\n\
+             <!-- message role=\"assistant\" model=\"llama3\" timestamp=\"2\" -->\n\
+             ## Assistant (llama3)\n\nUse <T: Ord> & friends\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_headings_inside_messages() {
+        let reply = message("assistant", "## Summary\n\nSort it.".to_string(), 2);
+        let session = Session::from_messages(
+            "work",
+            vec![message("user", "How do I sort?".to_string(), 1), reply],
+        );
+        let markdown = session.export(SessionFormat::Markdown).unwrap();
+        let messages = from_markdown(&markdown, "work").unwrap().messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content, "## Summary\n\nSort it.");
+        assert_eq!(messages[1].timestamp, 2);
+
+        // Without markers only role headings start a message
+        let written = "# Notes\n\n## User\n\nHi\n\n## Assistant\n\n## Summary\n\nHello\n";
+        let messages = from_markdown(written, "notes").unwrap().messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].content, "## Summary\n\nHello");
+    }
+
+    #[test]
+    fn test_chatgpt_conversation_keeps_branches() {
+        let export = json!([{
+            "title": "Sorting in Rust",
+            "create_time": 1700000000.5,
+            "current_node": "d",
+            "mapping": {
+                "root": {"message": null, "parent": null, "children": ["sys"]},
+                "sys": {"message": {"author": {"role": "system"}, "content": {"content_type": "text", "parts": [""]}}, "parent": "root", "children": ["a"]},
+                "a": {"message": {"author": {"role": "user"}, "create_time": 1700000001.0, "content": {"content_type": "text", "parts": ["How do I sort?"]}}, "parent": "sys", "children": ["b", "d"]},
+                "b": {"message": {"author": {"role": "assistant"}, "content": {"content_type": "text", "parts": ["Use sort()"]}, "metadata": {"model_slug": "gpt-4o"}}, "parent": "a", "children": []},
+                "d": {"message": {"author": {"role": "assistant"}, "content": {"content_type": "text", "parts": ["Use sort_by()"]}}, "parent": "a", "children": []}
+            }
+        }]);
+
+        let sessions = from_json(export, "conversations").unwrap();
+        let session = &sessions[0];
+        assert_eq!(session.name, "sorting-in-rust");
+        assert_eq!(session.title.as_deref(), Some("Sorting in Rust"));
+        assert_eq!(session.created_at, 1700000000);
+        assert_eq!(session.nodes.len(), 3);
+        assert_eq!(session.children(Some(0)), vec![1, 2]);
+        assert_eq!(session.nodes[1].message.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(session.messages()[1].content, "Use sort_by()");
+    }
+
+    #[test]
+    fn test_claude_conversation() {
+        let export = json!([{
+            "name": "Sorting",
+            "created_at": "2024-05-01T10:00:00Z",
+            "chat_messages": [
+                {"sender": "human", "text": "How do I sort?", "created_at": "2024-05-01T10:00:00Z"},
+                {"sender": "assistant", "text": "", "content": [{"type": "text", "text": "Use sort()"}]}
+            ]
+        }]);
+
+        let sessions = from_json(export, "conversations").unwrap();
+        let messages = sessions[0].messages();
+        assert_eq!(sessions[0].name, "sorting");
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[0].timestamp, 1714557600);
+        assert_eq!(messages[1].content, "Use sort()");
+    }
+}
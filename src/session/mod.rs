@@ -1,3 +1,8 @@
+mod format;
+mod search;
 mod store;
+mod tree;
 
-pub use store::SessionStore;
\ No newline at end of file
+pub use format::{import, SessionFormat};
+pub use search::{SearchHit, SearchQuery};
+pub use store::{recent_turns, Session, SessionMessage, SessionNode, SessionStore};
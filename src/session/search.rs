@@ -0,0 +1,258 @@
+use super::{Session, SessionMessage};
+use crate::utils::LlmCliError;
+use anyhow::Result;
+use chrono::{NaiveDate, TimeZone};
+use regex::{Regex, RegexBuilder};
+use sled::Tree;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Range;
+
+/// What `session search` looks for. Text queries match messages containing
+/// every word (as a word prefix, ignoring case); regex queries are matched
+/// as given, ignoring case.
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub regex: bool,
+    pub role: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct SearchHit {
+    pub session: String,
+    /// The message's id within the session (see `session show --tree`)
+    pub id: usize,
+    pub message: SessionMessage,
+    /// Byte range of the first match in the message content
+    pub matched: Range<usize>,
+}
+
+/// A compiled `SearchQuery`.
+pub(super) struct Matcher {
+    patterns: Vec<Regex>,
+    /// Words to look up in the index; `None` when every message must be scanned
+    words: Option<Vec<String>>,
+}
+
+impl Matcher {
+    pub(super) fn new(query: &SearchQuery) -> Result<Self> {
+        if query.regex {
+            let pattern = RegexBuilder::new(&query.text)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| LlmCliError::InvalidInput(format!("Invalid regex: {}", e)))?;
+            return Ok(Self {
+                patterns: vec![pattern],
+                words: None,
+            });
+        }
+
+        let words = tokenize(&query.text);
+        if words.is_empty() {
+            return Err(LlmCliError::InvalidInput(
+                "Search query has no words to match; use --regex to search for symbols".to_string(),
+            )
+            .into());
+        }
+        let patterns = words
+            .iter()
+            .map(|word| {
+                RegexBuilder::new(&format!(r"\b{}", regex::escape(word)))
+                    .case_insensitive(true)
+                    .build()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            patterns,
+            words: Some(words),
+        })
+    }
+
+    /// Where `message` matches the query and passes its filters.
+    pub(super) fn find(
+        &self,
+        query: &SearchQuery,
+        message: &SessionMessage,
+    ) -> Option<Range<usize>> {
+        if query
+            .role
+            .as_ref()
+            .is_some_and(|role| *role != message.role)
+        {
+            return None;
+        }
+        if query.model.is_some() && query.model != message.model {
+            return None;
+        }
+        if query.since.is_some() || query.until.is_some() {
+            let day = chrono::Local
+                .timestamp_opt(message.timestamp, 0)
+                .single()?
+                .date_naive();
+            if query.since.is_some_and(|since| day < since)
+                || query.until.is_some_and(|until| day > until)
+            {
+                return None;
+            }
+        }
+
+        let mut found = self
+            .patterns
+            .iter()
+            .map(|pattern| pattern.find(&message.content).map(|m| m.range()));
+        let first = found.next()??;
+        found.all(|m| m.is_some()).then_some(first)
+    }
+
+    /// Sessions and message ids that may match, from the index; `None` when
+    /// the query cannot use it.
+    pub(super) fn candidates(&self, index: &Tree) -> Result<Option<BTreeSet<(String, usize)>>> {
+        let Some(words) = &self.words else {
+            return Ok(None);
+        };
+
+        let mut candidates: Option<BTreeSet<(String, usize)>> = None;
+        for word in words {
+            let mut postings = BTreeSet::new();
+            for item in index.scan_prefix(word.as_bytes()) {
+                let (key, _) = item?;
+                if let Some(posting) = decode_posting(&key) {
+                    postings.insert(posting);
+                }
+            }
+            candidates = Some(match candidates {
+                Some(found) => found.intersection(&postings).cloned().collect(),
+                None => postings,
+            });
+        }
+        Ok(candidates)
+    }
+}
+
+/// The distinct lowercased words of `text`.
+pub(super) fn tokenize(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| seen.insert(word.clone()))
+        .collect()
+}
+
+/// Adds a posting for every word of every message in `session`.
+pub(super) fn index_session(index: &Tree, session: &Session) -> Result<()> {
+    for (id, node) in session.nodes.iter().enumerate() {
+        for word in tokenize(&node.message.content) {
+            index.insert(posting_key(&word, &session.name, id), &[])?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the postings `index_session` added for `session`.
+pub(super) fn unindex_session(index: &Tree, session: &Session) -> Result<()> {
+    for (id, node) in session.nodes.iter().enumerate() {
+        for word in tokenize(&node.message.content) {
+            index.remove(posting_key(&word, &session.name, id))?;
+        }
+    }
+    Ok(())
+}
+
+/// Postings are keyed `word \0 session \0 id`, so a prefix scan over a word
+/// finds every message containing a word starting with it.
+fn posting_key(word: &str, session: &str, id: usize) -> Vec<u8> {
+    let mut key = Vec::with_capacity(word.len() + session.len() + 10);
+    key.extend_from_slice(word.as_bytes());
+    key.push(0);
+    key.extend_from_slice(session.as_bytes());
+    key.push(0);
+    key.extend_from_slice(&(id as u64).to_be_bytes());
+    key
+}
+
+fn decode_posting(key: &[u8]) -> Option<(String, usize)> {
+    let start = key.iter().position(|&b| b == 0)? + 1;
+    let end = key.len().checked_sub(9).filter(|&end| end >= start)?;
+    let session = String::from_utf8(key[start..end].to_vec()).ok()?;
+    let id = u64::from_be_bytes(key[end + 1..].try_into().ok()?);
+    Some((session, id as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> SessionMessage {
+        SessionMessage {
+            timestamp: 0,
+            ..SessionMessage::new(role, content.to_string())
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Rust's borrow_checker, RUST and rust!"),
+            vec!["rust", "s", "borrow_checker", "and"]
+        );
+        assert!(tokenize("?!").is_empty());
+    }
+
+    #[test]
+    fn test_index_lookup_and_matching() {
+        let index = sled::Config::new()
+            .temporary(true)
+            .open()
+            .unwrap()
+            .open_tree("index")
+            .unwrap();
+        let session = Session::from_messages(
+            "work",
+            vec![
+                message("user", "How do lifetimes work in Rust?"),
+                message(
+                    "assistant",
+                    "Lifetimes tell the borrow checker how long references live.",
+                ),
+            ],
+        );
+        index_session(&index, &session).unwrap();
+
+        let query = SearchQuery {
+            text: "LIFETIME rust".to_string(),
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&query).unwrap();
+        let candidates = matcher.candidates(&index).unwrap().unwrap();
+        assert_eq!(candidates, BTreeSet::from([("work".to_string(), 0)]));
+        assert_eq!(matcher.find(&query, &session.nodes[0].message), Some(7..15));
+
+        let query = SearchQuery {
+            text: "live".to_string(),
+            role: Some("user".to_string()),
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&query).unwrap();
+        assert_eq!(matcher.candidates(&index).unwrap().unwrap().len(), 1);
+        assert_eq!(matcher.find(&query, &session.nodes[1].message), None);
+
+        let query = SearchQuery {
+            text: r"borrow\s+check".to_string(),
+            regex: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&query).unwrap();
+        assert!(matcher.candidates(&index).unwrap().is_none());
+        assert_eq!(
+            matcher.find(&query, &session.nodes[1].message),
+            Some(19..31)
+        );
+
+        unindex_session(&index, &session).unwrap();
+        assert!(index.is_empty());
+    }
+}
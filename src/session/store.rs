@@ -1,3 +1,5 @@
+use super::search::{self, Matcher, SearchHit, SearchQuery};
+use crate::api::{ChatResponse, FinishReason, Usage};
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
@@ -9,16 +11,101 @@ pub struct SessionMessage {
     pub role: String,
     pub content: String,
     pub timestamp: i64,
+    /// Token usage of the response, for assistant messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+    /// The model that wrote the response, for assistant messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// How long the response took, for assistant messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl SessionMessage {
+    pub fn new(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+            timestamp: chrono::Utc::now().timestamp(),
+            usage: None,
+            finish_reason: None,
+            model: None,
+            provider: None,
+            latency_ms: None,
+        }
+    }
+
+    /// The assistant message for `response`, recording who wrote it and how
+    /// long it took.
+    pub fn reply(response: &ChatResponse, provider: &str, latency_ms: u64) -> Self {
+        Self {
+            usage: response.usage,
+            finish_reason: response.finish_reason.clone(),
+            model: Some(response.model.clone()).filter(|model| !model.is_empty()),
+            provider: Some(provider.to_string()),
+            latency_ms: Some(latency_ms),
+            ..Self::new("assistant", response.text.clone())
+        }
+    }
+}
+
+/// The last `max_history` messages of `history`, moved forward to the first
+/// question among them: providers reject a conversation that opens with a reply.
+pub fn recent_turns(history: &[SessionMessage], max_history: usize) -> &[SessionMessage] {
+    let recent = &history[history.len().saturating_sub(max_history)..];
+    let start = recent
+        .iter()
+        .position(|m| m.role == "user")
+        .unwrap_or(recent.len());
+    &recent[start..]
+}
+
+/// A conversation stored as a tree, so trying another reply or rewording an
+/// earlier question adds a branch instead of overwriting the original.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Session {
     pub name: String,
-    pub messages: Vec<SessionMessage>,
+    /// Every message on every branch; a message's id is its index here
+    pub nodes: Vec<SessionNode>,
+    /// The last message of the active branch
+    pub head: Option<usize>,
+    /// Set from the first question unless given explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionNode {
+    /// The message this one follows; `None` for the first message of a branch
+    pub parent: Option<usize>,
+    #[serde(flatten)]
+    pub message: SessionMessage,
+}
+
+/// Sessions as stored before they became trees.
+#[derive(Deserialize)]
+struct FlatSession {
+    name: String,
+    messages: Vec<SessionMessage>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// Version of the stored session format: 1 kept a flat list of messages,
+/// 2 a tree of them, 3 added the search index and 4 titles.
+const SCHEMA_VERSION: u64 = 4;
+
 pub struct SessionStore {
     db: Db,
 }
@@ -27,8 +114,58 @@ impl SessionStore {
     pub fn new() -> Result<Self> {
         let db_path = Self::get_db_path()?;
         let db = sled::open(db_path)?;
+        Self::migrate(&db)?;
         Ok(Self { db })
     }
+
+    /// Brings sessions written by older versions up to `SCHEMA_VERSION`.
+    fn migrate(db: &Db) -> Result<()> {
+        let meta = db.open_tree("meta")?;
+        let version = match meta.get("schema_version")? {
+            Some(bytes) => u64::from_be_bytes(bytes.as_ref().try_into()?),
+            None => 1,
+        };
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        if version < 2 {
+            for item in db.iter() {
+                let (key, value) = item?;
+                // An interrupted migration leaves some sessions converted
+                let flat: FlatSession = match serde_json::from_slice(&value) {
+                    Ok(flat) => flat,
+                    Err(_) if serde_json::from_slice::<Session>(&value).is_ok() => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                let mut session = Session::from_messages(&flat.name, flat.messages);
+                session.created_at = flat.created_at;
+                session.updated_at = flat.updated_at;
+                db.insert(key, serde_json::to_vec(&session)?)?;
+            }
+        }
+
+        if version < 4 {
+            for item in db.iter() {
+                let (key, value) = item?;
+                let mut session: Session = serde_json::from_slice(&value)?;
+                if session.title.is_none() {
+                    session.title = session.auto_title();
+                    db.insert(key, serde_json::to_vec(&session)?)?;
+                }
+            }
+        }
+
+        let index = db.open_tree("index")?;
+        index.clear()?;
+        for item in db.iter() {
+            let (_, value) = item?;
+            search::index_session(&index, &serde_json::from_slice(&value)?)?;
+        }
+        meta.insert("schema_version", &SCHEMA_VERSION.to_be_bytes())?;
+        db.flush()?;
+        Ok(())
+    }
     
     fn get_db_path() -> Result<PathBuf> {
         let proj_dirs = ProjectDirs::from("com", "llm-cli", "llm-cli")
@@ -41,6 +178,12 @@ impl SessionStore {
     }
     
     pub fn save_session(&self, session: &Session) -> Result<()> {
+        let index = self.db.open_tree("index")?;
+        if let Some(old) = self.load_session(&session.name)? {
+            search::unindex_session(&index, &old)?;
+        }
+        search::index_session(&index, session)?;
+
         let key = session.name.as_bytes();
         let value = serde_json::to_vec(session)?;
         self.db.insert(key, value)?;
@@ -60,6 +203,9 @@ impl SessionStore {
     }
     
     pub fn delete_session(&self, name: &str) -> Result<()> {
+        if let Some(old) = self.load_session(name)? {
+            search::unindex_session(&self.db.open_tree("index")?, &old)?;
+        }
         let key = name.as_bytes();
         self.db.remove(key)?;
         self.db.flush()?;
@@ -79,17 +225,54 @@ impl SessionStore {
     
     pub fn add_message(&self, session_name: &str, message: SessionMessage) -> Result<()> {
         let mut session = self.load_session(session_name)?
-            .unwrap_or_else(|| Session {
-                name: session_name.to_string(),
-                messages: Vec::new(),
-                created_at: chrono::Utc::now().timestamp(),
-                updated_at: chrono::Utc::now().timestamp(),
-            });
+            .unwrap_or_else(|| Session::new(session_name));
         
-        session.messages.push(message);
+        session.push(message);
         session.updated_at = chrono::Utc::now().timestamp();
         
         self.save_session(&session)?;
         Ok(())
     }
+
+    /// Messages on any branch of any session that match `query`, by session
+    /// name and message id.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let matcher = Matcher::new(query)?;
+        // Without index candidates (a regex query), every message is scanned
+        let candidates = matcher.candidates(&self.db.open_tree("index")?)?;
+        let names = match &candidates {
+            Some(candidates) => {
+                // Candidates are sorted by session name
+                let mut names: Vec<String> =
+                    candidates.iter().map(|(name, _)| name.clone()).collect();
+                names.dedup();
+                names
+            }
+            None => self.list_sessions()?,
+        };
+
+        let mut hits = Vec::new();
+        for name in names {
+            let Some(session) = self.load_session(&name)? else {
+                continue;
+            };
+            for (id, node) in session.nodes.iter().enumerate() {
+                if candidates
+                    .as_ref()
+                    .is_some_and(|c| !c.contains(&(name.clone(), id)))
+                {
+                    continue;
+                }
+                if let Some(matched) = matcher.find(query, &node.message) {
+                    hits.push(SearchHit {
+                        session: name.clone(),
+                        id,
+                        message: node.message.clone(),
+                        matched,
+                    });
+                }
+            }
+        }
+        Ok(hits)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,260 @@
+use super::{Session, SessionMessage, SessionNode};
+use crate::utils::LlmCliError;
+use anyhow::Result;
+
+impl Session {
+    pub fn new(name: &str) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            name: name.to_string(),
+            nodes: Vec::new(),
+            head: None,
+            title: None,
+            description: None,
+            tags: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// A session holding `messages` as its only branch.
+    pub fn from_messages(name: &str, messages: Vec<SessionMessage>) -> Self {
+        let mut session = Self::new(name);
+        for message in messages {
+            session.push(message);
+        }
+        session
+    }
+
+    /// Message ids from the first message down to `id`.
+    pub fn path_to(&self, id: usize) -> Vec<usize> {
+        let mut path: Vec<usize> =
+            std::iter::successors(Some(id), |&id| self.nodes[id].parent).collect();
+        path.reverse();
+        path
+    }
+
+    /// Ids of the messages on the active branch, oldest first.
+    pub fn active_ids(&self) -> Vec<usize> {
+        self.head.map(|head| self.path_to(head)).unwrap_or_default()
+    }
+
+    /// The conversation along the active branch.
+    pub fn messages(&self) -> Vec<SessionMessage> {
+        self.active_ids()
+            .into_iter()
+            .map(|id| self.nodes[id].message.clone())
+            .collect()
+    }
+
+    /// Ids of the replies to `parent`, or of the first messages for `None`.
+    pub fn children(&self, parent: Option<usize>) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].parent == parent)
+            .collect()
+    }
+
+    /// Number of distinct conversation paths, i.e. messages without replies.
+    pub fn branch_count(&self) -> usize {
+        (0..self.nodes.len())
+            .filter(|&id| self.children(Some(id)).is_empty())
+            .count()
+    }
+
+    /// Whether every message follows one stored before it and the head
+    /// exists, as `push` guarantees; other trees would break walking them.
+    pub fn is_well_formed(&self) -> bool {
+        let nodes_ok = self
+            .nodes
+            .iter()
+            .enumerate()
+            .all(|(id, node)| node.parent.is_none_or(|parent| parent < id));
+        nodes_ok && self.head.is_none_or(|head| head < self.nodes.len())
+    }
+
+    /// Appends a message to the active branch, titling the session after
+    /// its first question.
+    pub fn push(&mut self, message: SessionMessage) {
+        self.nodes.push(SessionNode {
+            parent: self.head,
+            message,
+        });
+        self.head = Some(self.nodes.len() - 1);
+        if self.title.is_none() {
+            self.title = self.auto_title();
+        }
+    }
+
+    /// The first line of the first question, cut at a word to at most
+    /// `TITLE_LENGTH` characters.
+    pub fn auto_title(&self) -> Option<String> {
+        const TITLE_LENGTH: usize = 50;
+        let question = self.nodes.iter().find(|n| n.message.role == "user")?;
+        let line = question
+            .message
+            .content
+            .lines()
+            .find(|l| !l.trim().is_empty())?;
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.chars().count() <= TITLE_LENGTH {
+            return Some(line);
+        }
+
+        let cut: String = line.chars().take(TITLE_LENGTH - 1).collect();
+        let cut = match cut.rfind(' ') {
+            Some(space) => &cut[..space],
+            None => &cut,
+        };
+        Some(format!("{}…", cut))
+    }
+
+    /// Makes `messages` the active branch. Messages already stored along the
+    /// same path are reused, so a rewritten history (an undo, a retried reply)
+    /// becomes a new branch and the old one is kept.
+    pub fn set_messages(&mut self, messages: Vec<SessionMessage>) {
+        self.head = None;
+        for message in messages {
+            let existing = self.children(self.head).into_iter().find(|&id| {
+                let stored = &self.nodes[id].message;
+                stored.role == message.role
+                    && stored.content == message.content
+                    && stored.timestamp == message.timestamp
+            });
+            match existing {
+                Some(id) => self.head = Some(id),
+                None => self.push(message),
+            }
+        }
+    }
+
+    /// A new session with the conversation from the first message down to
+    /// message `at`, which may be on any branch.
+    pub fn fork(&self, at: usize, name: &str) -> Result<Session> {
+        self.node(at)?;
+        let messages = self
+            .path_to(at)
+            .into_iter()
+            .map(|id| self.nodes[id].message.clone())
+            .collect();
+        Ok(Self::from_messages(name, messages))
+    }
+
+    /// Adds a replacement for user message `at` as a sibling of it and makes
+    /// it the head of the active branch. Returns the new message's id.
+    pub fn edit(&mut self, at: usize, content: String) -> Result<usize> {
+        let node = self.node(at)?;
+        if node.message.role != "user" {
+            return Err(LlmCliError::SessionError(format!(
+                "Message #{} is a {} message; only user messages can be edited",
+                at, node.message.role
+            ))
+            .into());
+        }
+
+        self.head = node.parent;
+        self.push(SessionMessage::new("user", content));
+        Ok(self.nodes.len() - 1)
+    }
+
+    fn node(&self, id: usize) -> Result<&SessionNode> {
+        self.nodes.get(id).ok_or_else(|| {
+            LlmCliError::SessionError(format!("Session '{}' has no message #{}", self.name, id))
+                .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str, timestamp: i64) -> SessionMessage {
+        SessionMessage {
+            timestamp,
+            ..SessionMessage::new(role, content.to_string())
+        }
+    }
+
+    #[test]
+    fn test_rewritten_history_becomes_a_branch() {
+        let question = message("user", "Hi", 1);
+        let mut session = Session::from_messages(
+            "work",
+            vec![question.clone(), message("assistant", "Hello", 2)],
+        );
+
+        // A retried reply replaces the last message
+        session.set_messages(vec![question, message("assistant", "Hey there", 3)]);
+
+        assert_eq!(session.nodes.len(), 3);
+        assert_eq!(session.children(Some(0)), vec![1, 2]);
+        assert_eq!(session.active_ids(), vec![0, 2]);
+        assert_eq!(session.messages()[1].content, "Hey there");
+        assert_eq!(session.branch_count(), 2);
+    }
+
+    #[test]
+    fn test_fork_and_edit() {
+        let mut session = Session::from_messages(
+            "work",
+            vec![
+                message("user", "Hi", 1),
+                message("assistant", "Hello", 2),
+                message("user", "Tell me a joke", 3),
+                message("assistant", "No", 4),
+            ],
+        );
+
+        let fork = session.fork(1, "copy").unwrap();
+        assert_eq!(fork.name, "copy");
+        assert_eq!(fork.messages().len(), 2);
+
+        let edited = session.edit(2, "Tell me a story".to_string()).unwrap();
+        assert_eq!(session.nodes[edited].parent, Some(1));
+        assert_eq!(session.active_ids(), vec![0, 1, edited]);
+        assert_eq!(session.nodes.len(), 5);
+
+        assert!(session.edit(1, "Hello?".to_string()).is_err());
+        assert!(session.fork(9, "nope").is_err());
+    }
+
+    #[test]
+    fn test_title_from_first_question() {
+        let session = Session::from_messages(
+            "work",
+            vec![
+                message("assistant", "Welcome back", 1),
+                message("user", "\nHow   do lifetimes work?\nI am confused", 2),
+            ],
+        );
+        assert_eq!(session.title.as_deref(), Some("How do lifetimes work?"));
+
+        let long = "Explain the difference between ownership, borrowing and lifetimes";
+        let session = Session::from_messages("long", vec![message("user", long, 1)]);
+        assert_eq!(
+            session.title.as_deref(),
+            Some("Explain the difference between ownership,…")
+        );
+    }
+
+    #[test]
+    fn test_recent_turns_start_with_a_question() {
+        let history = [
+            message("user", "Hi", 1),
+            message("assistant", "Hello", 2),
+            message("user", "Tell me a joke", 3),
+            message("assistant", "No", 4),
+        ];
+
+        let recent = crate::session::recent_turns(&history, 3);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].content, "Tell me a joke");
+
+        // A history left uneven by a failed append
+        let recent = crate::session::recent_turns(&history[1..], 3);
+        assert_eq!(recent[0].content, "Tell me a joke");
+
+        assert_eq!(crate::session::recent_turns(&history, 10).len(), 4);
+        assert!(crate::session::recent_turns(&history[3..], 10).is_empty());
+    }
+}
@@ -1,30 +1,792 @@
-use anyhow::Result;
+use super::{FrontMatter, TemplateStore};
+use crate::utils::{LlmCliError, Result};
 use std::collections::HashMap;
 
+/// Partials may include other partials, but not without bound.
+const MAX_PARTIAL_DEPTH: usize = 16;
+
+/// A template variable: a single string, or a list for `{{#each}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(text) => !text.is_empty(),
+            Value::List(items) => !items.is_empty(),
+        }
+    }
+
+    fn items(&self) -> Vec<String> {
+        match self {
+            Value::Text(text) if text.is_empty() => Vec::new(),
+            Value::Text(text) => vec![text.clone()],
+            Value::List(items) => items.clone(),
+        }
+    }
+
+    fn into_text(self) -> String {
+        match self {
+            Value::Text(text) => text,
+            Value::List(items) => items.join("\n"),
+        }
+    }
+}
+
+/// Line and column (both 1-based) of a tag in the template source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Filter {
+    name: String,
+    arg: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Expr {
+    path: String,
+    filters: Vec<Filter>,
+    position: Position,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Expr(Expr),
+    If {
+        condition: Expr,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        list: Expr,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Partial {
+        name: String,
+        position: Position,
+    },
+}
+
+/// Renders prompt templates written in a small Handlebars-like language:
+///
+/// - `{{name}}` and `{{name | filter: arg | ...}}` substitutions
+/// - `{{#if name}}...{{else}}...{{/if}}` and `{{#unless name}}...{{/unless}}`
+/// - `{{#each list}}...{{this}}...{{@index}}...{{/each}}`
+/// - `{{> other-template}}` partial includes
+/// - `{{! comments }}`, and `\{{` for a literal `{{`
+///
+/// Filters: `default: "x"`, `upper`, `lower`, `trim`, `indent: N`,
+/// `code-fence: lang` and `join: ", "`.
 pub struct TemplateEngine {
     templates: HashMap<String, String>,
+    store: Option<TemplateStore>,
 }
 
 impl TemplateEngine {
     pub fn new() -> Self {
         Self {
             templates: HashMap::new(),
+            store: None,
         }
     }
-    
+
+    /// Falls back to the template store for templates and partials that
+    /// were not added explicitly.
+    pub fn with_store(mut self, store: TemplateStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
     pub fn add_template(&mut self, name: String, content: String) {
         self.templates.insert(name, content);
     }
-    
-    pub fn render(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
-        let template = self.templates.get(name)
-            .ok_or_else(|| anyhow::anyhow!("Template '{}' not found", name))?;
-        
-        let mut result = template.clone();
-        for (key, value) in variables {
-            result = result.replace(&format!("{{{{{}}}}}", key), value);
+
+    pub fn render(&self, name: &str, variables: &HashMap<String, Value>) -> Result<String> {
+        let mut renderer = Renderer {
+            engine: self,
+            variables,
+            missing: Vec::new(),
+        };
+
+        let output = renderer.render_template(name, &Scope::root(), 0)?;
+
+        if !renderer.missing.is_empty() {
+            let missing: Vec<String> = renderer
+                .missing
+                .iter()
+                .map(|(path, position)| format!("{} ({})", path, position))
+                .collect();
+            return Err(LlmCliError::TemplateError(format!(
+                "Template '{}' has unresolved placeholders: {}. Pass them with --var key=value",
+                name,
+                missing.join(", ")
+            )));
+        }
+
+        Ok(output)
+    }
+
+    fn source(&self, name: &str) -> Result<String> {
+        if let Some(content) = self.templates.get(name) {
+            return Ok(content.clone());
+        }
+
+        let stored = match &self.store {
+            Some(store) => store
+                .load(name)
+                .map_err(|e| LlmCliError::TemplateError(e.to_string()))?,
+            None => None,
+        };
+
+        let content = stored
+            .ok_or_else(|| LlmCliError::TemplateError(format!("Template '{}' not found", name)))?;
+
+        // Partials are whole template files; their headers are not part of the text
+        let (_, body) = FrontMatter::split(&content)?;
+        Ok(body.to_string())
+    }
+}
+
+/// Loop variables visible inside `{{#each}}`.
+struct Scope {
+    this: Option<String>,
+    index: usize,
+    first: bool,
+    last: bool,
+}
+
+impl Scope {
+    fn root() -> Self {
+        Self {
+            this: None,
+            index: 0,
+            first: false,
+            last: false,
+        }
+    }
+}
+
+struct Renderer<'a> {
+    engine: &'a TemplateEngine,
+    variables: &'a HashMap<String, Value>,
+    missing: Vec<(String, Position)>,
+}
+
+impl Renderer<'_> {
+    fn render_template(&mut self, name: &str, scope: &Scope, depth: usize) -> Result<String> {
+        let source = self.engine.source(name)?;
+        let nodes = parse(&source)
+            .map_err(|e| LlmCliError::TemplateError(format!("{} in template '{}'", e, name)))?;
+
+        let mut output = String::new();
+        self.render_nodes(&nodes, scope, depth, &mut output)?;
+        Ok(output)
+    }
+
+    fn render_nodes(
+        &mut self,
+        nodes: &[Node],
+        scope: &Scope,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Expr(expr) => match self.evaluate(expr, scope)? {
+                    Some(value) => output.push_str(&value.into_text()),
+                    None => self.missing.push((expr.path.clone(), expr.position)),
+                },
+                Node::If {
+                    condition,
+                    negate,
+                    then,
+                    otherwise,
+                } => {
+                    let truthy = self
+                        .evaluate(condition, scope)?
+                        .is_some_and(|value| value.is_truthy());
+                    let branch = if truthy != *negate { then } else { otherwise };
+                    self.render_nodes(branch, scope, depth, output)?;
+                }
+                Node::Each {
+                    list,
+                    body,
+                    otherwise,
+                } => {
+                    let items = self
+                        .evaluate(list, scope)?
+                        .map(|value| value.items())
+                        .unwrap_or_default();
+
+                    if items.is_empty() {
+                        self.render_nodes(otherwise, scope, depth, output)?;
+                    }
+
+                    let count = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        let inner = Scope {
+                            this: Some(item),
+                            index,
+                            first: index == 0,
+                            last: index + 1 == count,
+                        };
+                        self.render_nodes(body, &inner, depth, output)?;
+                    }
+                }
+                Node::Partial { name, position } => {
+                    if depth >= MAX_PARTIAL_DEPTH {
+                        return Err(LlmCliError::TemplateError(format!(
+                            "Partials nested too deeply including '{}' at {}",
+                            name, position
+                        )));
+                    }
+                    let rendered = self.render_template(name, scope, depth + 1)?;
+                    output.push_str(&rendered);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lookup(&self, path: &str, scope: &Scope) -> Option<Value> {
+        match path {
+            "this" | "." => scope.this.clone().map(Value::Text),
+            "@index" => Some(Value::Text(scope.index.to_string())),
+            "@first" => Some(Value::Text(
+                if scope.first { "true" } else { "" }.to_string(),
+            )),
+            "@last" => Some(Value::Text(
+                if scope.last { "true" } else { "" }.to_string(),
+            )),
+            _ => self.variables.get(path).cloned(),
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr, scope: &Scope) -> Result<Option<Value>> {
+        let mut value = self.lookup(&expr.path, scope);
+
+        for filter in &expr.filters {
+            value = apply_filter(filter, value)
+                .map_err(|e| LlmCliError::TemplateError(format!("{} at {}", e, expr.position)))?;
+        }
+
+        Ok(value)
+    }
+}
+
+fn apply_filter(
+    filter: &Filter,
+    value: Option<Value>,
+) -> std::result::Result<Option<Value>, String> {
+    let arg = filter.arg.as_deref();
+
+    if filter.name == "default" {
+        return Ok(match value {
+            Some(value) if value.is_truthy() => Some(value),
+            _ => Some(Value::Text(arg.unwrap_or_default().to_string())),
+        });
+    }
+
+    let Some(value) = value else {
+        return Ok(None);
+    };
+
+    let text = match filter.name.as_str() {
+        "join" => match value {
+            Value::List(items) => items.join(arg.unwrap_or(", ")),
+            Value::Text(text) => text,
+        },
+        "upper" => value.into_text().to_uppercase(),
+        "lower" => value.into_text().to_lowercase(),
+        "trim" => value.into_text().trim().to_string(),
+        "indent" => {
+            let width: usize = match arg {
+                Some(arg) => arg
+                    .parse()
+                    .map_err(|_| format!("indent expects a number, got '{}'", arg))?,
+                None => 2,
+            };
+            let padding = " ".repeat(width);
+            value
+                .into_text()
+                .split('\n')
+                .map(|line| {
+                    if line.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{}{}", padding, line)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        "code-fence" | "code_fence" => {
+            let text = value.into_text();
+            format!(
+                "```{}\n{}\n```",
+                arg.unwrap_or_default(),
+                text.trim_end_matches('\n')
+            )
+        }
+        other => return Err(format!("Unknown filter '{}'", other)),
+    };
+
+    Ok(Some(Value::Text(text)))
+}
+
+const FILTERS: &[&str] = &[
+    "default",
+    "join",
+    "upper",
+    "lower",
+    "trim",
+    "indent",
+    "code-fence",
+    "code_fence",
+];
+
+/// A parse error, rendered as "message at line L, column C".
+#[derive(Debug)]
+struct ParseError {
+    message: String,
+    position: Position,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+fn error<T>(message: impl Into<String>, position: Position) -> std::result::Result<T, ParseError> {
+    Err(ParseError {
+        message: message.into(),
+        position,
+    })
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Tag { body: String, position: Position },
+}
+
+impl Token {
+    /// Block, else and comment tags vanish together with their line when
+    /// they stand alone on it, so templates can be laid out readably.
+    fn is_standalone_candidate(&self) -> bool {
+        match self {
+            Token::Tag { body, .. } => {
+                body.starts_with('#')
+                    || body.starts_with('/')
+                    || body.starts_with('!')
+                    || body == "else"
+            }
+            Token::Text(_) => false,
+        }
+    }
+}
+
+fn tokenize(source: &str) -> std::result::Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut line = 1;
+    let mut column = 1;
+    let mut rest = source;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("\\{{") {
+            text.push_str("{{");
+            column += 3;
+            rest = after;
+            continue;
+        }
+
+        if rest.starts_with("{{") {
+            let position = Position { line, column };
+            let Some(end) = rest.find("}}") else {
+                return error("Unclosed '{{'", position);
+            };
+
+            let inner = &rest[2..end];
+            let consumed = &rest[..end + 2];
+            for c in consumed.chars() {
+                if c == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+            }
+            rest = &rest[end + 2..];
+
+            if !text.is_empty() {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+            }
+            tokens.push(Token::Tag {
+                body: inner.trim().to_string(),
+                position,
+            });
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        text.push(c);
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+
+    strip_standalone_lines(&mut tokens);
+    Ok(tokens)
+}
+
+fn strip_standalone_lines(tokens: &mut [Token]) {
+    // Decide on the original text first: stripping one tag's line must not
+    // change whether the tag on the next line counts as standalone.
+    let standalone: Vec<usize> = (0..tokens.len())
+        .filter(|&i| tokens[i].is_standalone_candidate())
+        .filter(|&i| {
+            let before_ok = match i.checked_sub(1).map(|j| &tokens[j]) {
+                None => true,
+                Some(Token::Text(text)) => {
+                    let tail = &text[text.rfind('\n').map_or(0, |p| p + 1)..];
+                    tail.chars().all(|c| c == ' ' || c == '\t') && (text.contains('\n') || i == 1)
+                }
+                Some(Token::Tag { .. }) => false,
+            };
+            let after_ok = match tokens.get(i + 1) {
+                None => true,
+                Some(Token::Text(text)) => {
+                    let head = &text[..text.find('\n').map_or(text.len(), |p| p + 1)];
+                    head.trim_end_matches(['\n', '\r'])
+                        .chars()
+                        .all(|c| c == ' ' || c == '\t')
+                }
+                Some(Token::Tag { .. }) => false,
+            };
+            before_ok && after_ok
+        })
+        .collect();
+
+    for i in standalone {
+        if let Some(Token::Text(text)) = i.checked_sub(1).map(|j| &mut tokens[j]) {
+            let keep = text.rfind('\n').map_or(0, |p| p + 1);
+            text.truncate(keep);
+        }
+        if let Some(Token::Text(text)) = tokens.get_mut(i + 1) {
+            let skip = text.find('\n').map_or(text.len(), |p| p + 1);
+            text.replace_range(..skip, "");
         }
-        
-        Ok(result)
     }
-}
\ No newline at end of file
+}
+
+fn parse_expr(body: &str, position: Position) -> std::result::Result<Expr, ParseError> {
+    let mut segments = split_unquoted(body, '|').into_iter();
+    let path = segments.next().unwrap_or_default().trim().to_string();
+
+    let valid_path = !path.is_empty()
+        && path
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '@'));
+    if !valid_path {
+        return error(format!("Invalid expression '{}'", body), position);
+    }
+
+    let mut filters = Vec::new();
+    for segment in segments {
+        let (name, arg) = match segment.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(unquote(arg.trim()))),
+            None => (segment.trim(), None),
+        };
+        if !FILTERS.contains(&name) {
+            return error(format!("Unknown filter '{}'", name), position);
+        }
+        filters.push(Filter {
+            name: name.to_string(),
+            arg,
+        });
+    }
+
+    Ok(Expr {
+        path,
+        filters,
+        position,
+    })
+}
+
+fn split_unquoted(input: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+
+    for c in input.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (Some(q), _) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (None, _) if c == separator => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+fn unquote(arg: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = arg.strip_prefix(quote).and_then(|a| a.strip_suffix(quote)) {
+            return inner.replace("\\n", "\n");
+        }
+    }
+    arg.to_string()
+}
+
+/// An open block waiting for its closing tag.
+struct Frame {
+    kind: String,
+    expr: Option<Expr>,
+    position: Position,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+fn parse(source: &str) -> std::result::Result<Vec<Node>, ParseError> {
+    let mut root: Vec<Node> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+
+    fn target<'a>(root: &'a mut Vec<Node>, stack: &'a mut [Frame]) -> &'a mut Vec<Node> {
+        match stack.last_mut() {
+            Some(frame) => frame.otherwise.as_mut().unwrap_or(&mut frame.then),
+            None => root,
+        }
+    }
+
+    for token in tokenize(source)? {
+        let (body, position) = match token {
+            Token::Text(text) => {
+                target(&mut root, &mut stack).push(Node::Text(text));
+                continue;
+            }
+            Token::Tag { body, position } => (body, position),
+        };
+
+        if body.starts_with('!') {
+            continue;
+        }
+
+        if let Some(block) = body.strip_prefix('#') {
+            let (kind, arg) = block.split_once(char::is_whitespace).unwrap_or((block, ""));
+            if !matches!(kind, "if" | "unless" | "each") {
+                return error(format!("Unknown block '#{}'", kind), position);
+            }
+            if arg.trim().is_empty() {
+                return error(format!("'#{}' needs a variable", kind), position);
+            }
+            stack.push(Frame {
+                kind: kind.to_string(),
+                expr: Some(parse_expr(arg, position)?),
+                position,
+                then: Vec::new(),
+                otherwise: None,
+            });
+            continue;
+        }
+
+        if body == "else" {
+            match stack.last_mut() {
+                Some(frame) if frame.otherwise.is_none() => frame.otherwise = Some(Vec::new()),
+                Some(_) => return error("Duplicate '{{else}}'", position),
+                None => return error("'{{else}}' outside of a block", position),
+            }
+            continue;
+        }
+
+        if let Some(kind) = body.strip_prefix('/') {
+            let kind = kind.trim();
+            let Some(frame) = stack.pop() else {
+                return error(format!("Unexpected '{{{{/{}}}}}'", kind), position);
+            };
+            if frame.kind != kind {
+                return error(
+                    format!(
+                        "Expected '{{{{/{}}}}}' to close the block opened at {}, found '{{{{/{}}}}}'",
+                        frame.kind, frame.position, kind
+                    ),
+                    position,
+                );
+            }
+
+            let expr = frame.expr.expect("blocks always carry an expression");
+            let otherwise = frame.otherwise.unwrap_or_default();
+            let node = match frame.kind.as_str() {
+                "each" => Node::Each {
+                    list: expr,
+                    body: frame.then,
+                    otherwise,
+                },
+                kind => Node::If {
+                    condition: expr,
+                    negate: kind == "unless",
+                    then: frame.then,
+                    otherwise,
+                },
+            };
+            target(&mut root, &mut stack).push(node);
+            continue;
+        }
+
+        if let Some(name) = body.strip_prefix('>') {
+            let name = name.trim();
+            if name.is_empty() {
+                return error("Partial needs a template name", position);
+            }
+            target(&mut root, &mut stack).push(Node::Partial {
+                name: name.to_string(),
+                position,
+            });
+            continue;
+        }
+
+        let expr = parse_expr(&body, position)?;
+        target(&mut root, &mut stack).push(Node::Expr(expr));
+    }
+
+    if let Some(frame) = stack.pop() {
+        return error(
+            format!("Unclosed '{{{{#{}}}}}'", frame.kind),
+            frame.position,
+        );
+    }
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, vars: &[(&str, Value)]) -> Result<String> {
+        let mut engine = TemplateEngine::new();
+        engine.add_template("t".to_string(), template.to_string());
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        engine.render("t", &vars)
+    }
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_substitution_and_filters() {
+        let out = render(
+            "{{ name | upper }} / {{lang | default: \"rust\"}} / {{pad | trim}}",
+            &[("name", text("ada")), ("pad", text("  x  "))],
+        )
+        .unwrap();
+        assert_eq!(out, "ADA / rust / x");
+    }
+
+    #[test]
+    fn test_indent_and_code_fence() {
+        let out = render(
+            "{{code | code-fence: rust | indent: 2}}",
+            &[("code", text("fn main() {}\n"))],
+        )
+        .unwrap();
+        assert_eq!(out, "  ```rust\n  fn main() {}\n  ```");
+    }
+
+    #[test]
+    fn test_conditionals_and_loops_strip_standalone_lines() {
+        let template = "Files:\n{{#each files}}\n- {{@index}}: {{this}}\n{{else}}\n(none)\n{{/each}}\n{{#if strict}}\nBe strict.\n{{else}}\nBe kind.\n{{/if}}\n";
+        let out = render(
+            template,
+            &[(
+                "files",
+                Value::List(vec!["a.rs".to_string(), "b.rs".to_string()]),
+            )],
+        )
+        .unwrap();
+        assert_eq!(out, "Files:\n- 0: a.rs\n- 1: b.rs\nBe kind.\n");
+
+        let out = render(template, &[("strict", text("yes"))]).unwrap();
+        assert_eq!(out, "Files:\n(none)\nBe strict.\n");
+    }
+
+    #[test]
+    fn test_partials() {
+        let mut engine = TemplateEngine::new();
+        engine.add_template("header".to_string(), "You are {{role}}.".to_string());
+        engine.add_template("main".to_string(), "{{> header}}\n{{query}}".to_string());
+        let vars = HashMap::from([
+            ("role".to_string(), text("a reviewer")),
+            ("query".to_string(), text("Go")),
+        ]);
+        assert_eq!(
+            engine.render("main", &vars).unwrap(),
+            "You are a reviewer.\nGo"
+        );
+    }
+
+    #[test]
+    fn test_unresolved_placeholders_are_reported() {
+        let err = render("Hi {{name}}\n{{#if x}}{{y}}{{/if}}{{z}}", &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Template error: Template 't' has unresolved placeholders: name (line 1, column 4), z (line 2, column 22). Pass them with --var key=value"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_have_positions() {
+        let err = render("ok\n  {{#if a}}\n{{/each}}", &[]).unwrap_err();
+        assert!(err.to_string().contains(
+            "close the block opened at line 2, column 3, found '{{/each}}' at line 3, column 1"
+        ));
+
+        let err = render("{{#if a}}", &[]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unclosed '{{#if}}' at line 1, column 1"));
+
+        let err = render("x {{a | shout}}", &[]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown filter 'shout' at line 1, column 3"));
+    }
+}
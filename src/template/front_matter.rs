@@ -0,0 +1,227 @@
+use super::Value;
+use crate::utils::{LlmCliError, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Settings a template declares about itself in a header block:
+///
+/// ```text
+/// +++                                  ---
+/// model = "gpt-4o"                     model: gpt-4o
+/// temperature = 0.2                    temperature: 0.2
+/// [[variables]]                        variables:
+/// name = "lang"                          - name: lang
+/// default = "rust"                         default: rust
+/// +++                                  ---
+/// ```
+///
+/// TOML is fenced by `+++`, YAML by `---`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrontMatter {
+    pub description: Option<String>,
+    pub model: Option<String>,
+    pub system: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub variables: Vec<VariableSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariableSpec {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: VariableType,
+    pub description: Option<String>,
+    /// Variables are required unless they are marked optional or have a default
+    pub required: Option<bool>,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    List,
+    Number,
+    Boolean,
+}
+
+impl std::fmt::Display for VariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            VariableType::String => "string",
+            VariableType::List => "list",
+            VariableType::Number => "number",
+            VariableType::Boolean => "boolean",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl VariableSpec {
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(self.default.is_none())
+    }
+
+    fn check(&self, value: &Value) -> Result<()> {
+        let invalid = |text: &str| {
+            LlmCliError::TemplateError(format!(
+                "Variable '{}' expects a {}, got '{}'",
+                self.name, self.kind, text
+            ))
+        };
+
+        match (self.kind, value) {
+            (VariableType::Number, Value::Text(text)) => {
+                text.trim().parse::<f64>().map_err(|_| invalid(text))?;
+            }
+            (VariableType::Boolean, Value::Text(text)) => {
+                text.trim().parse::<bool>().map_err(|_| invalid(text))?;
+            }
+            (
+                VariableType::String | VariableType::Number | VariableType::Boolean,
+                Value::List(_),
+            ) => {
+                return Err(LlmCliError::TemplateError(format!(
+                    "Variable '{}' expects a single {}, but was given several values",
+                    self.name, self.kind
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl FrontMatter {
+    /// Separates the header from the template body. Templates without a
+    /// header get default (empty) front matter.
+    pub fn split(content: &str) -> Result<(FrontMatter, &str)> {
+        for fence in ["+++", "---"] {
+            let Some(rest) = content.strip_prefix(fence).and_then(|rest| {
+                rest.strip_prefix('\n')
+                    .or_else(|| rest.strip_prefix("\r\n"))
+            }) else {
+                continue;
+            };
+
+            let (header, after) = if let Some(after) = rest.strip_prefix(fence) {
+                // An empty header: the closing fence directly follows the opening one
+                ("", after)
+            } else {
+                let closing = format!("\n{}", fence);
+                let end = rest.find(&closing).ok_or_else(|| {
+                    LlmCliError::TemplateError(format!(
+                        "Front matter opened with '{}' is never closed",
+                        fence
+                    ))
+                })?;
+                (&rest[..end], &rest[end + closing.len()..])
+            };
+            let body = after
+                .strip_prefix("\r\n")
+                .or_else(|| after.strip_prefix('\n'))
+                .unwrap_or(after);
+
+            let front_matter = if fence == "+++" {
+                toml::from_str(header).map_err(|e| {
+                    LlmCliError::TemplateError(format!("Invalid TOML front matter: {}", e))
+                })?
+            } else if header.trim().is_empty() {
+                FrontMatter::default()
+            } else {
+                serde_yaml::from_str(header).map_err(|e| {
+                    LlmCliError::TemplateError(format!("Invalid YAML front matter: {}", e))
+                })?
+            };
+
+            return Ok((front_matter, body));
+        }
+
+        Ok((FrontMatter::default(), content))
+    }
+
+    /// Fills in declared defaults, then checks that every required variable is
+    /// present and that values match their declared types.
+    pub fn apply(&self, variables: &mut HashMap<String, Value>) -> Result<()> {
+        let mut missing = Vec::new();
+
+        for spec in &self.variables {
+            if !variables.contains_key(&spec.name) {
+                if let Some(default) = &spec.default {
+                    variables.insert(spec.name.clone(), Value::Text(default.clone()));
+                }
+            }
+
+            match variables.get_mut(&spec.name) {
+                Some(value) => {
+                    spec.check(value)?;
+                    if let (VariableType::List, Value::Text(text)) = (spec.kind, &*value) {
+                        *value = Value::List(vec![text.clone()]);
+                    }
+                }
+                None if spec.is_required() => match &spec.description {
+                    Some(description) => missing.push(format!("{} ({})", spec.name, description)),
+                    None => missing.push(spec.name.clone()),
+                },
+                None => {}
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(LlmCliError::TemplateError(format!(
+                "Missing required variables: {}. Pass them with --var key=value",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_front_matter() {
+        let content = "+++\nmodel = \"gpt-4o\"\ntemperature = 0.2\n\n[[variables]]\nname = \"lang\"\ndefault = \"rust\"\n\n[[variables]]\nname = \"files\"\ntype = \"list\"\n+++\nReview {{lang}}\n";
+        let (front_matter, body) = FrontMatter::split(content).unwrap();
+
+        assert_eq!(body, "Review {{lang}}\n");
+        assert_eq!(front_matter.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(front_matter.variables[1].kind, VariableType::List);
+
+        let mut vars = HashMap::from([("files".to_string(), Value::Text("a.rs".to_string()))]);
+        front_matter.apply(&mut vars).unwrap();
+        assert_eq!(vars["lang"], Value::Text("rust".to_string()));
+        assert_eq!(vars["files"], Value::List(vec!["a.rs".to_string()]));
+    }
+
+    #[test]
+    fn test_yaml_front_matter_validation() {
+        let content = "---\nsystem: Be brief\nvariables:\n  - name: count\n    type: number\n    description: How many items\n---\nList {{count}} items";
+        let (front_matter, body) = FrontMatter::split(content).unwrap();
+        assert_eq!(body, "List {{count}} items");
+        assert_eq!(front_matter.system.as_deref(), Some("Be brief"));
+
+        let err = front_matter.apply(&mut HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("count (How many items)"));
+
+        let mut vars = HashMap::from([("count".to_string(), Value::Text("many".to_string()))]);
+        let err = front_matter.apply(&mut vars).unwrap_err();
+        assert!(err.to_string().contains("expects a number"));
+    }
+
+    #[test]
+    fn test_plain_template_has_no_front_matter() {
+        let (front_matter, body) = FrontMatter::split("--- not a header").unwrap();
+        assert!(front_matter.model.is_none());
+        assert_eq!(body, "--- not a header");
+    }
+}
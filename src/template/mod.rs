@@ -1,2 +1,7 @@
 mod engine;
+mod front_matter;
+mod store;
 
+pub use engine::{TemplateEngine, Value};
+pub use front_matter::FrontMatter;
+pub use store::{TemplateScope, TemplateStore};
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use directories::ProjectDirs;
+use std::path::PathBuf;
+
+const EXTENSION: &str = "tmpl";
+
+/// Where a template lives. Project templates shadow global ones of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateScope {
+    Project,
+    Global,
+}
+
+impl std::fmt::Display for TemplateScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateScope::Project => write!(f, "project"),
+            TemplateScope::Global => write!(f, "global"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TemplateEntry {
+    pub name: String,
+    pub scope: TemplateScope,
+    pub path: PathBuf,
+}
+
+/// Prompt templates stored as `<name>.tmpl` files.
+///
+/// Project templates live in `./templates` next to config.toml; global ones
+/// in the user's config directory.
+pub struct TemplateStore {
+    project_dir: PathBuf,
+    global_dir: Option<PathBuf>,
+}
+
+impl TemplateStore {
+    pub fn new() -> Result<Self> {
+        let global_dir = ProjectDirs::from("com", "llm-cli", "llm-cli")
+            .map(|dirs| dirs.config_dir().join("templates"));
+
+        Ok(Self {
+            project_dir: PathBuf::from("templates"),
+            global_dir,
+        })
+    }
+
+    fn dir(&self, scope: TemplateScope) -> Result<&PathBuf> {
+        match scope {
+            TemplateScope::Project => Ok(&self.project_dir),
+            TemplateScope::Global => self
+                .global_dir
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Could not determine config directory")),
+        }
+    }
+
+    fn path(&self, name: &str, scope: TemplateScope) -> Result<PathBuf> {
+        Self::validate_name(name)?;
+        Ok(self.dir(scope)?.join(format!("{}.{}", name, EXTENSION)))
+    }
+
+    fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            anyhow::bail!(
+                "Invalid template name '{}': use letters, digits, '-' and '_'",
+                name
+            );
+        }
+        Ok(())
+    }
+
+    /// Locates a template, preferring the project copy over the global one.
+    pub fn find(&self, name: &str) -> Result<Option<TemplateEntry>> {
+        for scope in [TemplateScope::Project, TemplateScope::Global] {
+            if scope == TemplateScope::Global && self.global_dir.is_none() {
+                continue;
+            }
+
+            let path = self.path(name, scope)?;
+            if path.is_file() {
+                return Ok(Some(TemplateEntry {
+                    name: name.to_string(),
+                    scope,
+                    path,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn load(&self, name: &str) -> Result<Option<String>> {
+        match self.find(name)? {
+            Some(entry) => Ok(Some(std::fs::read_to_string(&entry.path)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, name: &str, content: &str, scope: TemplateScope) -> Result<PathBuf> {
+        let path = self.path(name, scope)?;
+        std::fs::create_dir_all(self.dir(scope)?)?;
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    /// Deletes the template from the given scope, or from wherever `find`
+    /// resolves it when no scope is given. Returns the removed entry.
+    pub fn delete(
+        &self,
+        name: &str,
+        scope: Option<TemplateScope>,
+    ) -> Result<Option<TemplateEntry>> {
+        let entry = match scope {
+            Some(scope) => {
+                let path = self.path(name, scope)?;
+                path.is_file().then(|| TemplateEntry {
+                    name: name.to_string(),
+                    scope,
+                    path,
+                })
+            }
+            None => self.find(name)?,
+        };
+
+        if let Some(entry) = &entry {
+            std::fs::remove_file(&entry.path)?;
+        }
+        Ok(entry)
+    }
+
+    /// All templates, with project templates shadowing global ones of the same name.
+    pub fn list(&self) -> Result<Vec<TemplateEntry>> {
+        let mut entries: Vec<TemplateEntry> = Vec::new();
+
+        for scope in [TemplateScope::Project, TemplateScope::Global] {
+            let Ok(dir) = self.dir(scope) else {
+                continue;
+            };
+            if !dir.is_dir() {
+                continue;
+            }
+
+            for item in std::fs::read_dir(dir)? {
+                let path = item?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                    continue;
+                }
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                if entries.iter().any(|e| e.name == name) {
+                    continue;
+                }
+
+                entries.push(TemplateEntry {
+                    name: name.to_string(),
+                    scope,
+                    path,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
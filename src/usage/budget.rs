@@ -0,0 +1,248 @@
+use super::{record_day, UsageLedger, UsageRecord};
+use crate::api::{Message, Usage};
+use crate::config::manager::{BudgetAction, BudgetConfig, BudgetLimits, ModelInfo};
+use crate::utils::LlmCliError;
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Worst-case cost in USD of a request: its prompt plus a reply that uses the
+/// whole `max_tokens` allowance. `None` if the model has no prices.
+fn estimate_cost(model: &ModelInfo, messages: &[Message], max_tokens: u32) -> Option<f64> {
+    model.cost(&Usage {
+        input: estimate_tokens(messages),
+        output: max_tokens as u64,
+        ..Default::default()
+    })
+}
+
+/// Rough size of a prompt in tokens.
+pub fn estimate_tokens(messages: &[Message]) -> u64 {
+    // About four characters per token, plus a few tokens of framing per message
+    messages
+        .iter()
+        .map(|m| m.content.chars().count().div_ceil(4) as u64 + 4)
+        .sum()
+}
+
+/// A request about to be sent, for budget checks.
+pub struct PlannedCost<'a> {
+    /// Provider and estimated cost of each model that may answer it: the
+    /// model asked first, then the fallbacks it may fall over to
+    pub candidates: Vec<(&'a str, Option<f64>)>,
+}
+
+impl<'a> PlannedCost<'a> {
+    /// The cost of sending `messages` to a fallback chain with a reply
+    /// allowance of `max_tokens`, which fallbacks cap at their own limit.
+    pub fn new(
+        chain: impl IntoIterator<Item = &'a ModelInfo>,
+        messages: &[Message],
+        max_tokens: u32,
+    ) -> Self {
+        let candidates = chain
+            .into_iter()
+            .enumerate()
+            .map(|(i, model)| {
+                let max_tokens = if i == 0 {
+                    max_tokens
+                } else {
+                    model.reply_limit(max_tokens)
+                };
+                (
+                    model.provider.as_str(),
+                    estimate_cost(model, messages, max_tokens),
+                )
+            })
+            .collect();
+        Self { candidates }
+    }
+}
+
+/// Checks that the planned requests fit the global and per-provider budgets,
+/// given what the ledger says was already spent today and this month. A
+/// request counts at the cost of its most expensive model in each budget, as
+/// any of its fallbacks may end up answering it.
+///
+/// Depending on `budget.on_exceed` an overrun either fails with
+/// `LlmCliError::BudgetExceeded` or asks for confirmation first.
+pub fn enforce_budget(
+    config: &BudgetConfig,
+    ledger: Option<&UsageLedger>,
+    planned: &[PlannedCost],
+) -> Result<()> {
+    if config.limits.is_unset() && config.providers.is_empty() {
+        return Ok(());
+    }
+
+    // Requests to unpriced models cannot be held to a budget
+    if planned
+        .iter()
+        .flat_map(|cost| &cost.candidates)
+        .all(|(_, estimate)| estimate.is_none())
+    {
+        return Ok(());
+    }
+
+    // Without the ledger, spend so far is unknown; guessing zero would let
+    // every request through
+    let Some(ledger) = ledger else {
+        anyhow::bail!("Budgets are set but the usage ledger is unavailable to check spend against");
+    };
+    let records = ledger.records()?;
+    let today = chrono::Local::now().date_naive();
+
+    let mut scopes = vec![("Global".to_string(), None, &config.limits)];
+    for (provider, limits) in &config.providers {
+        scopes.push((
+            format!("Provider '{}'", provider),
+            Some(provider.as_str()),
+            limits,
+        ));
+    }
+
+    for (scope, provider, limits) in scopes {
+        let in_scope = |p: &str| provider.is_none_or(|provider| provider == p);
+        let estimate: f64 = planned
+            .iter()
+            .map(|cost| {
+                cost.candidates
+                    .iter()
+                    .filter(|(provider, _)| in_scope(provider))
+                    .filter_map(|(_, estimate)| *estimate)
+                    .fold(0.0, f64::max)
+            })
+            .sum();
+        if estimate == 0.0 {
+            continue;
+        }
+
+        let scoped: Vec<&UsageRecord> = records.iter().filter(|r| in_scope(&r.provider)).collect();
+        if let Some(error) = check_limits(&scope, limits, &scoped, estimate, today) {
+            confirm_overrun(config.on_exceed, error)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_limits(
+    scope: &str,
+    limits: &BudgetLimits,
+    records: &[&UsageRecord],
+    estimate: f64,
+    today: NaiveDate,
+) -> Option<LlmCliError> {
+    let month_start = today.with_day(1).unwrap_or(today);
+    let periods = [
+        ("daily", limits.daily, today),
+        ("monthly", limits.monthly, month_start),
+    ];
+
+    periods.into_iter().find_map(|(period, limit, first_day)| {
+        let limit = limit?;
+        let spent: f64 = records
+            .iter()
+            .filter(|r| record_day(r).is_some_and(|day| day >= first_day))
+            .filter_map(|r| r.cost)
+            .sum();
+
+        (spent + estimate > limit).then(|| LlmCliError::BudgetExceeded {
+            scope: scope.to_string(),
+            period: period.to_string(),
+            limit,
+            spent,
+            estimate,
+        })
+    })
+}
+
+fn confirm_overrun(action: BudgetAction, error: LlmCliError) -> Result<()> {
+    if action == BudgetAction::Refuse || !std::io::stdin().is_terminal() {
+        return Err(error.into());
+    }
+
+    eprint!("{}. Send anyway? [y/N] ", error);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(error.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(provider: &str, timestamp: i64, cost: f64) -> UsageRecord {
+        UsageRecord {
+            timestamp,
+            command: "ask".to_string(),
+            model: "gpt-4o".to_string(),
+            provider: provider.to_string(),
+            session: None,
+            usage: Usage::default(),
+            cost: Some(cost),
+        }
+    }
+
+    #[test]
+    fn test_limits_count_spend_in_period() {
+        let now = chrono::Local::now();
+        let today = now.date_naive();
+        let records = [
+            record("openai", now.timestamp(), 0.75),
+            record("openai", now.timestamp() - 40 * 86_400, 50.0),
+        ];
+        let records: Vec<&UsageRecord> = records.iter().collect();
+        let limits = BudgetLimits {
+            daily: Some(1.0),
+            monthly: Some(10.0),
+        };
+
+        assert!(check_limits("Global", &limits, &records, 0.2, today).is_none());
+
+        let error = check_limits("Global", &limits, &records, 0.3, today).unwrap();
+        assert_eq!(error.exit_code(), 3);
+        assert!(matches!(
+            error,
+            LlmCliError::BudgetExceeded { ref period, spent, .. } if period == "daily" && spent == 0.75
+        ));
+    }
+
+    #[test]
+    fn test_unpriced_requests_skip_budgets() {
+        let config = BudgetConfig {
+            limits: BudgetLimits {
+                daily: Some(0.0),
+                monthly: None,
+            },
+            ..Default::default()
+        };
+        let planned = [PlannedCost {
+            candidates: vec![("local", None)],
+        }];
+
+        assert!(enforce_budget(&config, None, &planned).is_ok());
+    }
+
+    #[test]
+    fn test_budgets_fail_closed_without_ledger() {
+        let config = BudgetConfig {
+            limits: BudgetLimits {
+                daily: Some(100.0),
+                monthly: None,
+            },
+            ..Default::default()
+        };
+        let planned = [PlannedCost {
+            candidates: vec![("openai", Some(0.01))],
+        }];
+
+        let error = enforce_budget(&config, None, &planned).unwrap_err();
+        assert!(error.to_string().contains("usage ledger is unavailable"));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::api::Usage;
+use crate::config::manager::ModelInfo;
+use anyhow::Result;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another llm-cli process to finish with the ledger.
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// One billed request, as recorded in the ledger.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsageRecord {
+    pub timestamp: i64,
+    /// The command that made the request: `ask`, `chat` or `compare`
+    pub command: String,
+    pub model: String,
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub usage: Usage,
+    /// Cost in USD at the prices configured when the request was made;
+    /// `None` if the model had no prices
+    pub cost: Option<f64>,
+}
+
+impl UsageRecord {
+    pub fn new(command: &str, model: &ModelInfo, usage: Usage, session: Option<&str>) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().timestamp(),
+            command: command.to_string(),
+            model: model.name.clone(),
+            provider: model.provider.clone(),
+            session: session.map(str::to_string),
+            cost: model.cost(&usage),
+            usage,
+        }
+    }
+}
+
+/// Append-only log of token usage and cost, kept next to the session store.
+///
+/// Only one process at a time can open the database, so it is opened for
+/// each read or write rather than held; a long chat must not lock out an
+/// `ask` in another terminal.
+pub struct UsageLedger {
+    path: PathBuf,
+}
+
+impl UsageLedger {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            path: Self::get_db_path()?,
+        })
+    }
+
+    /// Opens the database, waiting up to `LOCK_WAIT` while another process
+    /// has it open.
+    fn open(&self) -> Result<Db> {
+        let start = Instant::now();
+        loop {
+            match sled::open(&self.path) {
+                Ok(db) => return Ok(db),
+                Err(sled::Error::Io(e)) if start.elapsed() < LOCK_WAIT => {
+                    log::debug!("Usage ledger busy, retrying: {}", e);
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn get_db_path() -> Result<PathBuf> {
+        let proj_dirs = ProjectDirs::from("com", "llm-cli", "llm-cli")
+            .ok_or_else(|| anyhow::anyhow!("Could not determine data directory"))?;
+
+        let data_dir = proj_dirs.data_dir();
+        std::fs::create_dir_all(data_dir)?;
+
+        Ok(data_dir.join("usage"))
+    }
+
+    pub fn record(&self, record: &UsageRecord) -> Result<()> {
+        let db = self.open()?;
+        // Monotonic big-endian ids keep records in the order they were made
+        let key = db.generate_id()?.to_be_bytes();
+        db.insert(key, serde_json::to_vec(record)?)?;
+        db.flush()?;
+        Ok(())
+    }
+
+    pub fn records(&self) -> Result<Vec<UsageRecord>> {
+        self.open()?
+            .iter()
+            .values()
+            .map(|value| Ok(serde_json::from_slice(&value?)?))
+            .collect()
+    }
+}
+
+/// Records a response's usage, if the provider reported any.
+///
+/// Accounting must never fail the request it accounts for, so problems (such
+/// as another llm-cli process holding the ledger for too long) are only
+/// logged.
+pub fn record_usage(
+    ledger: Option<&UsageLedger>,
+    command: &str,
+    model: &ModelInfo,
+    usage: Option<Usage>,
+    session: Option<&str>,
+) {
+    let (Some(ledger), Some(usage)) = (ledger, usage) else {
+        return;
+    };
+
+    let record = UsageRecord::new(command, model, usage, session);
+    if let Err(e) = ledger.record(&record) {
+        log::warn!("Could not record usage: {}", e);
+    }
+}
+
+/// Locates the ledger, logging rather than failing when there is no data
+/// directory.
+pub fn open_ledger() -> Option<UsageLedger> {
+    UsageLedger::new()
+        .map_err(|e| log::warn!("Could not open usage ledger: {}", e))
+        .ok()
+}
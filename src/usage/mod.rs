@@ -0,0 +1,7 @@
+mod budget;
+mod ledger;
+mod report;
+
+pub use budget::{enforce_budget, estimate_tokens, PlannedCost};
+pub use ledger::{open_ledger, record_usage, UsageLedger, UsageRecord};
+pub use report::{record_day, summarize, GroupBy, UsageSummary};
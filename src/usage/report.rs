@@ -0,0 +1,128 @@
+use super::UsageRecord;
+use chrono::{NaiveDate, TimeZone};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+
+/// How `usage` groups the ledger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum GroupBy {
+    #[default]
+    Day,
+    Model,
+    Provider,
+    /// Chat session; one-off requests are grouped by command, e.g. `(compare)`
+    Session,
+}
+
+/// Totals for one group of records.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UsageSummary {
+    pub key: String,
+    pub requests: usize,
+    pub input: u64,
+    pub output: u64,
+    pub cost: f64,
+    /// Requests whose model had no prices, so their cost is missing from `cost`
+    pub unpriced: usize,
+}
+
+impl UsageSummary {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.input += record.usage.input;
+        self.output += record.usage.output;
+        match record.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced += 1,
+        }
+    }
+}
+
+/// The local calendar day a record was made on.
+pub fn record_day(record: &UsageRecord) -> Option<NaiveDate> {
+    chrono::Local
+        .timestamp_opt(record.timestamp, 0)
+        .single()
+        .map(|time| time.date_naive())
+}
+
+fn group_key(record: &UsageRecord, by: GroupBy) -> String {
+    match by {
+        GroupBy::Day => record_day(record)
+            .map(|day| day.to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+        GroupBy::Model => record.model.clone(),
+        GroupBy::Provider => record.provider.clone(),
+        GroupBy::Session => record
+            .session
+            .clone()
+            .unwrap_or_else(|| format!("({})", record.command)),
+    }
+}
+
+/// Sums records per group, sorted by key, followed by the overall total.
+pub fn summarize(records: &[UsageRecord], by: GroupBy) -> (Vec<UsageSummary>, UsageSummary) {
+    let mut groups: BTreeMap<String, UsageSummary> = BTreeMap::new();
+    let mut total = UsageSummary {
+        key: "total".to_string(),
+        ..Default::default()
+    };
+
+    for record in records {
+        let key = group_key(record, by);
+        groups
+            .entry(key.clone())
+            .or_insert_with(|| UsageSummary {
+                key,
+                ..Default::default()
+            })
+            .add(record);
+        total.add(record);
+    }
+
+    (groups.into_values().collect(), total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Usage;
+
+    fn record(model: &str, session: Option<&str>, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            timestamp: 1_700_000_000,
+            command: "compare".to_string(),
+            model: model.to_string(),
+            provider: "openai".to_string(),
+            session: session.map(str::to_string),
+            usage: Usage {
+                input: 100,
+                output: 10,
+                ..Default::default()
+            },
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_summarize_by_model_and_session() {
+        let records = vec![
+            record("gpt-4o", None, Some(0.5)),
+            record("gpt-4o", Some("work"), Some(0.25)),
+            record("llama3", Some("work"), None),
+        ];
+
+        let (rows, total) = summarize(&records, GroupBy::Model);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].key, "gpt-4o");
+        assert_eq!(rows[0].requests, 2);
+        assert_eq!(rows[0].cost, 0.75);
+        assert_eq!(rows[1].unpriced, 1);
+        assert_eq!(total.input, 300);
+        assert_eq!(total.unpriced, 1);
+
+        let (rows, _) = summarize(&records, GroupBy::Session);
+        let keys: Vec<_> = rows.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, vec!["(compare)", "work"]);
+    }
+}
@@ -25,6 +25,86 @@ pub enum LlmCliError {
     
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    #[error("{scope} {period} budget of ${limit:.4} would be exceeded: ${spent:.4} spent, this request could cost up to ${estimate:.4}")]
+    BudgetExceeded {
+        scope: String,
+        period: String,
+        limit: f64,
+        spent: f64,
+        estimate: f64,
+    },
+
+    #[error("{provider} rejected the credentials: {message}")]
+    AuthFailed { provider: String, message: String },
+
+    #[error("{provider} rate limit reached: {message}")]
+    RateLimited { provider: String, message: String },
+
+    #[error("Request exceeds the model's context length: {message}")]
+    ContextLengthExceeded { provider: String, message: String },
+
+    #[error("{provider} content filter blocked the request: {message}")]
+    ContentFiltered { provider: String, message: String },
+
+    #[error("{provider} does not serve the requested model: {message}")]
+    ModelNotFound { provider: String, message: String },
+
+    #[error("{provider} is overloaded: {message}")]
+    Overloaded { provider: String, message: String },
+}
+
+impl LlmCliError {
+    /// Process exit status for this error, so scripts can tell failures apart.
+    ///
+    /// These are stable: 2 matches clap's status for bad arguments, and each
+    /// kind of provider failure gets its own code from 5 up.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LlmCliError::InvalidInput(_) => 2,
+            LlmCliError::BudgetExceeded { .. } => 3,
+            LlmCliError::ConfigError(_) => 4,
+            LlmCliError::AuthFailed { .. } => 5,
+            LlmCliError::RateLimited { .. } => 6,
+            LlmCliError::ContextLengthExceeded { .. } => 7,
+            LlmCliError::ContentFiltered { .. } => 8,
+            LlmCliError::ModelNotFound { .. } => 9,
+            LlmCliError::Overloaded { .. } => 10,
+            LlmCliError::ApiError(_) => 11,
+            _ => 1,
+        }
+    }
+
+    /// What the user can do about this error, if there is anything obvious.
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            LlmCliError::AuthFailed { provider, .. } => format!(
+                "Check the API key for '{}': set api.providers.{}.api_key or the variable named by its api_key_env",
+                provider, provider
+            ),
+            LlmCliError::RateLimited { .. } => {
+                "Wait a minute and try again, or raise retry.max_attempts to wait out the limit".to_string()
+            }
+            LlmCliError::ContextLengthExceeded { .. } => {
+                "Shorten the prompt, /clear the chat history, or pick a model with a larger context window".to_string()
+            }
+            LlmCliError::ContentFiltered { .. } => {
+                "The provider's safety system refused the request; rephrase it".to_string()
+            }
+            LlmCliError::ModelNotFound { provider, .. } => format!(
+                "Check the model's name in models.available and that your {} account can use it",
+                provider
+            ),
+            LlmCliError::Overloaded { .. } => {
+                "The provider is temporarily overloaded; try again shortly or switch with --model".to_string()
+            }
+            LlmCliError::ConfigError(_) => {
+                "Inspect the configuration with `llm-cli config show`".to_string()
+            }
+            _ => return None,
+        };
+        Some(hint)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, LlmCliError>;
\ No newline at end of file
@@ -1,2 +1,3 @@
 mod error;
 
+pub use error::{LlmCliError, Result};
@@ -35,4 +35,1122 @@ fn test_session_list() {
         .arg("list")
         .assert()
         .success();
-}
\ No newline at end of file
+}
+
+/// Creates an empty working directory with the given config.toml, so the CLI
+/// (which reads config from the current directory) runs in isolation.
+fn project_dir(name: &str, config: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("llm-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    dir
+}
+
+/// Runs the CLI inside `dir`, keeping sessions and global templates out of the
+/// user's real data and config directories.
+fn cli_in(dir: &std::path::Path) -> Command {
+    let mut cmd = Command::cargo_bin("llm-cli").unwrap();
+    cmd.current_dir(dir)
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("XDG_CONFIG_HOME", dir.join("xdg-config"));
+    cmd
+}
+
+fn local_provider_config(base_url: &str, streaming: bool) -> String {
+    format!(
+        r#"
+[api.providers.local]
+base_url = "{base_url}"
+protocol = "openai"
+enabled = true
+
+[models]
+default = "llama3"
+
+[[models.available]]
+name = "llama3"
+provider = "local"
+display_name = "Llama 3 (local)"
+
+[chat]
+temperature = 0.2
+max_tokens = 256
+streaming = {streaming}
+
+[session]
+auto_save = true
+max_history = 50
+
+[output]
+syntax_highlighting = false
+markdown_rendering = false
+"#
+    )
+}
+
+#[test]
+fn test_ask_local_openai_compatible_provider() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", mockito::Matcher::Missing)
+        .with_body(r#"{"id":"chatcmpl-1","choices":[{"message":{"content":"Hello from llama"}}]}"#)
+        .create();
+
+    let dir = project_dir(
+        "local-provider",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+
+    cli_in(&dir)
+        .args(["ask", "Hi"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello from llama"));
+
+    mock.assert();
+}
+
+#[test]
+fn test_chat_persists_session() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [{"role": "user", "content": "What is Rust?"}]
+        })))
+        .with_body(r#"{"id":"chatcmpl-2","choices":[{"message":{"content":"A language"}}]}"#)
+        .create();
+
+    let dir = project_dir(
+        "chat-session",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+
+    cli_in(&dir)
+        .args(["chat", "--session", "work"])
+        .write_stdin("What is Rust?\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("A language"));
+    mock.assert();
+
+    cli_in(&dir)
+        .args(["session", "show", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 2"));
+}
+
+#[test]
+fn test_chat_slash_commands() {
+    let mut server = mockito::Server::new();
+    let first = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [{"role": "user", "content": "Hi"}]
+        })))
+        .with_body(r#"{"id":"1","choices":[{"message":{"content":"Hello!"}}]}"#)
+        .create();
+    let second = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "temperature": 1.5,
+            "messages": [
+                {"role": "system", "content": "Be terse"},
+                {"role": "user", "content": "Hi again"}
+            ]
+        })))
+        .with_body(r#"{"id":"2","choices":[{"message":{"content":"Yo."}}]}"#)
+        .create();
+
+    let dir = project_dir(
+        "chat-commands",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+
+    cli_in(&dir)
+        .args(["chat", "--session", "slash"])
+        .write_stdin("Hi\n/undo\n/system Be terse\n/temperature 1.5\nHi again\n/bogus\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed the last exchange"))
+        .stdout(predicate::str::contains("Yo."))
+        .stderr(predicate::str::contains("Unknown command: /bogus"));
+    first.assert();
+    second.assert();
+
+    cli_in(&dir)
+        .args(["session", "show", "slash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 2"));
+}
+
+#[test]
+fn test_template_lifecycle() {
+    let dir = project_dir(
+        "templates",
+        &local_provider_config("http://127.0.0.1:1", false),
+    );
+
+    cli_in(&dir)
+        .args(["template", "create", "review"])
+        .write_stdin("Review this {{lang}} code:\n{{code}}\n")
+        .assert()
+        .success();
+    cli_in(&dir)
+        .args(["template", "create", "shared", "--global"])
+        .write_stdin("Summarize: {{text}}")
+        .assert()
+        .success();
+    assert!(dir.join("templates/review.tmpl").is_file());
+
+    cli_in(&dir)
+        .args(["template", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("review (project)"))
+        .stdout(predicate::str::contains("shared (global)"));
+
+    cli_in(&dir)
+        .args(["template", "show", "review"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Review this {{lang}} code:"));
+
+    cli_in(&dir)
+        .args(["template", "delete", "review"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Deleted project template 'review'",
+        ));
+    assert!(!dir.join("templates/review.tmpl").exists());
+}
+
+#[test]
+fn test_ask_with_template_vars() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [{"role": "user", "content": "Review this rust code:\nfn main() {}\n"}]
+        })))
+        .with_body(r#"{"id":"3","choices":[{"message":{"content":"Looks fine"}}]}"#)
+        .create();
+
+    let dir = project_dir(
+        "ask-template",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+    std::fs::create_dir_all(dir.join("templates")).unwrap();
+    std::fs::write(
+        dir.join("templates/code-review.tmpl"),
+        "Review this {{lang}} code:\n{{file}}",
+    )
+    .unwrap();
+    std::fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+
+    cli_in(&dir)
+        .args(["ask", "--template", "code-review"])
+        .args(["--var", "lang=rust", "--var", "file=@main.rs"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Looks fine"));
+    mock.assert();
+
+    cli_in(&dir)
+        .args(["ask", "--template", "code-review", "--var", "lang=rust"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unresolved placeholders: file"));
+}
+
+#[test]
+fn test_template_front_matter() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "model": "llama3",
+            "temperature": 0.5,
+            "max_completion_tokens": 64,
+            "messages": [
+                {"role": "system", "content": "You are a strict reviewer."},
+                {"role": "user", "content": "Review this python code"}
+            ]
+        })))
+        .with_body(r#"{"id":"4","choices":[{"message":{"content":"Done"}}]}"#)
+        .create();
+
+    let dir = project_dir(
+        "front-matter",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+    std::fs::create_dir_all(dir.join("templates")).unwrap();
+    std::fs::write(
+        dir.join("templates/review.tmpl"),
+        r#"+++
+description = "Code review prompt"
+model = "llama3"
+system = "You are a strict reviewer."
+temperature = 0.5
+max_tokens = 64
+
+[[variables]]
+name = "lang"
+description = "Language of the code"
+default = "python"
++++
+Review this {{lang}} code"#,
+    )
+    .unwrap();
+
+    cli_in(&dir)
+        .args(["template", "show", "review"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Code review prompt"))
+        .stdout(predicate::str::contains(
+            "lang (string, default: python) - Language of the code",
+        ));
+
+    cli_in(&dir)
+        .args(["ask", "--template", "review"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Done"));
+    mock.assert();
+}
+
+#[test]
+fn test_ask_output_formats() {
+    let mut server = mockito::Server::new();
+    // Streaming is on in the config, but these formats need the whole reply
+    server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({ "stream": false }),
+        ))
+        .with_body(
+            r#"{"id":"chatcmpl-9","choices":[{"message":{"content":"Forty-two"},"finish_reason":"stop"}],"usage":{"prompt_tokens":7,"completion_tokens":3}}"#,
+        )
+        .expect(4)
+        .create();
+
+    let dir = project_dir(
+        "ask-formats",
+        &local_provider_config(&format!("{}/v1", server.url()), true),
+    );
+
+    let output = cli_in(&dir)
+        .args(["ask", "Meaning of life?", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let envelope: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(envelope["id"], "chatcmpl-9");
+    assert_eq!(envelope["model"], "llama3");
+    assert_eq!(envelope["provider"], "local");
+    assert_eq!(envelope["text"], "Forty-two");
+    assert!(envelope["latency_ms"].is_u64());
+    assert_eq!(envelope["usage"]["input"], 7);
+    assert_eq!(envelope["usage"]["output"], 3);
+    assert_eq!(envelope["finish_reason"], "stop");
+
+    cli_in(&dir)
+        .args(["ask", "Meaning of life?", "--format", "raw", "--usage"])
+        .assert()
+        .success()
+        .stdout("Forty-two")
+        .stderr(predicate::str::contains(
+            "tokens: 7 in, 3 out · finish: stop",
+        ));
+
+    cli_in(&dir)
+        .args(["ask", "Meaning of life?", "--format", "raw"])
+        .assert()
+        .success()
+        .stdout("Forty-two");
+
+    cli_in(&dir)
+        .args(["ask", "Meaning of life?", "--format", "markdown"])
+        .args(["--output", "answer.md"])
+        .assert()
+        .success()
+        .stdout("");
+    let markdown = std::fs::read_to_string(dir.join("answer.md")).unwrap();
+    assert_eq!(
+        markdown,
+        "## Prompt\n\n> Meaning of life?\n\n## Response (llama3, local)\n\nForty-two\n"
+    );
+}
+
+#[test]
+fn test_usage_report() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("POST", "/v1/chat/completions")
+        .with_body(
+            r#"{"id":"chatcmpl-10","choices":[{"message":{"content":"Sure"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1000000,"completion_tokens":500000}}"#,
+        )
+        .expect(2)
+        .create();
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false).replace(
+        "display_name = \"Llama 3 (local)\"",
+        "display_name = \"Llama 3 (local)\"\ninput_price = 0.5\noutput_price = 2.0",
+    );
+    let dir = project_dir("usage-report", &config);
+
+    cli_in(&dir)
+        .args(["usage"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No usage recorded"));
+
+    cli_in(&dir).args(["ask", "Hi"]).assert().success();
+
+    // Another llm-cli process using the ledger only delays recording
+    let held = sled::open(dir.join("data/llm-cli/usage")).unwrap();
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        drop(held);
+    });
+    cli_in(&dir).args(["ask", "Hi"]).assert().success();
+    release.join().unwrap();
+
+    cli_in(&dir)
+        .args(["usage", "--by", "model"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"llama3\s+2\s+2000000\s+1000000\s+\$3\.0000").unwrap());
+
+    cli_in(&dir)
+        .args(["usage", "--by", "session", "--until", "2000-01-01"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No usage recorded"));
+}
+
+#[test]
+fn test_budget_refuses_request() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .expect(0)
+        .create();
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false).replace(
+        "display_name = \"Llama 3 (local)\"",
+        "display_name = \"Llama 3 (local)\"\ninput_price = 0.5\noutput_price = 2.0",
+    ) + "\n[budget.providers.local]\ndaily = 0.0001\n";
+    let dir = project_dir("budget", &config);
+
+    cli_in(&dir)
+        .args(["ask", "Hi"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Provider 'local' daily budget of $0.0001 would be exceeded",
+        ));
+
+    cli_in(&dir)
+        .args(["compare", "Hi", "--models", "llama3"])
+        .assert()
+        .code(3);
+
+    mock.assert();
+}
+
+#[test]
+fn test_budget_covers_fallback_providers() {
+    let mut server = mockito::Server::new();
+    let primary = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({"model": "llama3"}),
+        ))
+        .with_status(429)
+        .expect(0)
+        .create();
+    let fallback = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({"model": "mistral"}),
+        ))
+        .with_body(
+            r#"{"id":"fb-2","choices":[{"message":{"content":"Sure"}}],"usage":{"prompt_tokens":1000000,"completion_tokens":500000}}"#,
+        )
+        .expect(1)
+        .create();
+
+    let url = format!("{}/v1", server.url());
+    let config = local_provider_config(&url, false).replace(
+        "[chat]",
+        &format!(
+            r#"[[models.available]]
+name = "mistral"
+provider = "hosted"
+display_name = "Mistral (hosted)"
+input_price = 0.5
+output_price = 2.0
+
+[models.fallbacks]
+fast = ["llama3", "mistral"]
+
+[api.providers.hosted]
+base_url = "{url}"
+protocol = "openai"
+enabled = true
+
+[budget.providers.hosted]
+daily = 1.0
+
+[retry]
+max_attempts = 1
+
+[chat]"#
+        ),
+    );
+    let dir = project_dir("budget-fallback", &config);
+
+    // Spends $1.50 of the hosted provider's $1.00 daily budget
+    cli_in(&dir)
+        .args(["ask", "Hi", "--model", "mistral"])
+        .assert()
+        .success();
+
+    // The unpriced primary could fall over to the hosted model, so the
+    // request is refused before either is asked
+    cli_in(&dir)
+        .args(["ask", "Hi", "--model", "fast"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Provider 'hosted' daily budget of $1.0000 would be exceeded",
+        ));
+
+    primary.assert();
+    fallback.assert();
+}
+
+#[test]
+fn test_api_errors_map_to_exit_codes_and_hints() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("POST", "/v1/chat/completions")
+        .with_status(400)
+        .with_body(
+            r#"{"error": {"message": "This model's maximum context length is 8192 tokens", "code": "context_length_exceeded"}}"#,
+        )
+        .create();
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false);
+    let dir = project_dir("api-errors", &config);
+
+    cli_in(&dir)
+        .args(["ask", "Hi"])
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "Request exceeds the model's context length: This model's maximum context length is 8192 tokens",
+        ))
+        .stderr(predicate::str::contains("Hint: Shorten the prompt"));
+
+    cli_in(&dir)
+        .args(["ask", "Hi", "--model", "gpt-9"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Model 'gpt-9' not found"));
+}
+
+#[test]
+fn test_fallback_chain_reports_answering_model() {
+    let mut server = mockito::Server::new();
+    let primary = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({"model": "llama3"}),
+        ))
+        .with_status(429)
+        .expect(3)
+        .create();
+    let fallback = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({"model": "mistral"}),
+        ))
+        .with_body(r#"{"id":"fb-1","choices":[{"message":{"content":"Still here"}}]}"#)
+        .expect(3)
+        .create();
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false).replace(
+        "[chat]",
+        r#"[[models.available]]
+name = "mistral"
+provider = "local"
+display_name = "Mistral (local)"
+
+[models.fallbacks]
+fast = ["llama3", "mistral"]
+
+[retry]
+max_attempts = 1
+
+[chat]"#,
+    );
+    let dir = project_dir("fallback", &config);
+
+    let output = cli_in(&dir)
+        .args(["ask", "Hi", "--model", "fast", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let envelope: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(envelope["model"], "mistral");
+    assert_eq!(envelope["text"], "Still here");
+
+    cli_in(&dir)
+        .args(["ask", "Hi", "--model", "fast"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Still here"))
+        .stderr(predicate::str::contains(
+            "answered by fallback model mistral (llama3 failed)",
+        ));
+
+    cli_in(&dir)
+        .args(["compare", "Hi", "--models", "fast"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "MODEL: mistral (fallback for fast)",
+        ))
+        .stdout(predicate::str::contains("Still here"));
+
+    primary.assert();
+    fallback.assert();
+}
+
+#[test]
+fn test_model_alias_applies_model_defaults() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "model": "llama3",
+            "temperature": 0.5,
+            "max_completion_tokens": 64,
+            "messages": [
+                {"role": "system", "content": "Answer in one word"},
+                {"role": "user", "content": "Hi"}
+            ]
+        })))
+        .with_body(r#"{"id":"a-1","choices":[{"message":{"content":"Hello"}}]}"#)
+        .expect(2)
+        .create();
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false).replace(
+        "display_name = \"Llama 3 (local)\"",
+        "display_name = \"Llama 3 (local)\"\naliases = [\"llama\"]\ntemperature = 0.5\nmax_tokens = 64\nsystem = \"Answer in one word\"",
+    );
+    let dir = project_dir("model-alias", &config);
+
+    cli_in(&dir)
+        .args(["ask", "Hi", "--model", "llama"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello"));
+
+    cli_in(&dir)
+        .args(["compare", "Hi", "--models", "llama"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello"));
+
+    mock.assert();
+}
+
+#[test]
+fn test_system_prompt_flag_and_config_default() {
+    let mut server = mockito::Server::new();
+    let system_mock = |server: &mut mockito::Server, system: &str| {
+        server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "messages": [
+                    {"role": "system", "content": system},
+                    {"role": "user", "content": "Hi"}
+                ]
+            })))
+            .with_body(r#"{"id":"s-1","choices":[{"message":{"content":"Hello"}}]}"#)
+            .expect(1)
+            .create()
+    };
+    let configured = system_mock(&mut server, "Be kind");
+    let flagged = system_mock(&mut server, "Be terse");
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false).replace(
+        "streaming = false",
+        "streaming = false\nsystem = \"Be kind\"",
+    );
+    let dir = project_dir("system-prompt", &config);
+
+    cli_in(&dir).args(["ask", "Hi"]).assert().success();
+    cli_in(&dir)
+        .args(["ask", "Hi", "--system", "Be terse"])
+        .assert()
+        .success();
+
+    configured.assert();
+    flagged.assert();
+}
+
+#[test]
+fn test_ask_continues_session() {
+    let mut server = mockito::Server::new();
+    let mut turn = |messages: serde_json::Value, reply: &str| {
+        server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "messages": messages }),
+            ))
+            .with_body(format!(
+                r#"{{"id":"t","choices":[{{"message":{{"content":"{}"}}}}]}}"#,
+                reply
+            ))
+            .expect(1)
+            .create()
+    };
+    let first = turn(
+        serde_json::json!([{"role": "user", "content": "First"}]),
+        "One",
+    );
+    let second = turn(
+        serde_json::json!([
+            {"role": "user", "content": "First"},
+            {"role": "assistant", "content": "One"},
+            {"role": "user", "content": "Second"}
+        ]),
+        "Two",
+    );
+    // max_history = 2 keeps only the previous exchange
+    let third = turn(
+        serde_json::json!([
+            {"role": "user", "content": "Second"},
+            {"role": "assistant", "content": "Two"},
+            {"role": "user", "content": "Third"}
+        ]),
+        "Three",
+    );
+
+    let config = local_provider_config(&format!("{}/v1", server.url()), false)
+        .replace("max_history = 50", "max_history = 2");
+    let dir = project_dir("ask-session", &config);
+
+    for (question, answer) in [("First", "One"), ("Second", "Two"), ("Third", "Three")] {
+        cli_in(&dir)
+            .args(["ask", question, "--session", "script"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(answer));
+    }
+    first.assert();
+    second.assert();
+    third.assert();
+
+    cli_in(&dir)
+        .args(["session", "show", "script"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 6"));
+}
+
+#[test]
+fn test_session_branches_fork_and_migration() {
+    let dir = project_dir(
+        "session-tree",
+        &local_provider_config("http://127.0.0.1:1", false),
+    );
+
+    // A session written before sessions became trees
+    {
+        let db = sled::open(dir.join("data/llm-cli/sessions")).unwrap();
+        let legacy = serde_json::json!({
+            "name": "old",
+            "messages": [
+                {"role": "user", "content": "Hi", "timestamp": 1},
+                {"role": "assistant", "content": "Hello", "timestamp": 2},
+                {"role": "user", "content": "Tell me a joke", "timestamp": 3},
+                {"role": "assistant", "content": "No", "timestamp": 4}
+            ],
+            "created_at": 1,
+            "updated_at": 4
+        });
+        db.insert("old", serde_json::to_vec(&legacy).unwrap())
+            .unwrap();
+        // One an interrupted migration already converted
+        let converted = serde_json::json!({
+            "name": "done",
+            "nodes": [{"parent": null, "role": "user", "content": "Hi", "timestamp": 1}],
+            "head": 0,
+            "created_at": 1,
+            "updated_at": 1
+        });
+        db.insert("done", serde_json::to_vec(&converted).unwrap())
+            .unwrap();
+        db.flush().unwrap();
+    }
+
+    cli_in(&dir)
+        .args(["session", "show", "old"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 4"));
+    cli_in(&dir)
+        .args(["session", "show", "done"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 1"));
+    // Migrated sessions are indexed for search
+    cli_in(&dir)
+        .args(["session", "search", "joke"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old #2 user: Tell me a joke"));
+
+    cli_in(&dir)
+        .args(["session", "edit", "old", "--at", "2", "Tell me a story"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added message #4 as a new branch"))
+        .stdout(predicate::str::contains(
+            "`chat --session old` and `/retry`",
+        ));
+
+    cli_in(&dir)
+        .args(["session", "show", "old", "--tree"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 3"))
+        .stdout(predicate::str::contains("Branches: 2"))
+        .stdout(predicate::str::contains("├─ #2 user: Tell me a joke"))
+        .stdout(predicate::str::contains("│  #3 assistant: No"))
+        .stdout(predicate::str::contains("└─ #4 user: Tell me a story"));
+
+    cli_in(&dir)
+        .args(["session", "fork", "old", "--at", "3", "joke"])
+        .assert()
+        .success();
+    cli_in(&dir)
+        .args(["session", "show", "joke"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 4"));
+
+    cli_in(&dir)
+        .args(["session", "edit", "old", "--at", "3", "Yes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only user messages can be edited"));
+}
+
+#[test]
+fn test_session_search() {
+    let mut server = mockito::Server::new();
+    let _reply = server
+        .mock("POST", "/v1/chat/completions")
+        .with_body(
+            r#"{"id":"t","choices":[{"message":{"content":"Lifetimes tell the borrow checker how long references live."}}]}"#,
+        )
+        .expect(2)
+        .create();
+    let dir = project_dir(
+        "session-search",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+
+    for (question, session) in [
+        ("How do lifetimes work in Rust?", "rust"),
+        ("What about lifetimes in C++?", "cpp"),
+    ] {
+        cli_in(&dir)
+            .args(["ask", question, "--session", session])
+            .assert()
+            .success();
+    }
+
+    cli_in(&dir)
+        .args(["session", "search", "LIFETIMES rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "1 matching messages in 1 sessions",
+        ))
+        .stdout(predicate::str::contains(
+            "rust #0 user: How do lifetimes work in Rust?",
+        ));
+
+    cli_in(&dir)
+        .args(["session", "search", "lifetime", "--role", "assistant"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "2 matching messages in 2 sessions",
+        ))
+        .stdout(predicate::str::contains("cpp #1 assistant: Lifetimes tell"));
+
+    cli_in(&dir)
+        .args(["session", "search", "borrow", "--model", "gpt-4o"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No messages match 'borrow'"));
+    cli_in(&dir)
+        .args(["session", "search", "borrow", "--model", "llama3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 matching messages"));
+
+    cli_in(&dir)
+        .args(["session", "search", r"c\+\+", "--regex"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "cpp #0 user: What about lifetimes in C++?",
+        ));
+
+    cli_in(&dir)
+        .args(["session", "delete", "cpp"])
+        .assert()
+        .success();
+    cli_in(&dir)
+        .args(["session", "search", "lifetimes", "--since", "2000-01-01"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "2 matching messages in 1 sessions",
+        ));
+    cli_in(&dir)
+        .args(["session", "search", "lifetimes", "--until", "2000-01-01"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No messages match"));
+}
+
+#[test]
+fn test_session_export_and_import() {
+    let dir = project_dir(
+        "session-transfer",
+        &local_provider_config("http://127.0.0.1:1", false),
+    );
+
+    // A ChatGPT data export whose question was answered twice
+    let archive = dir.join("chatgpt.zip");
+    {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        zip.start_file(
+            "conversations.json",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        let conversations = serde_json::json!([{
+            "title": "Sorting in Rust",
+            "create_time": 1700000000.0,
+            "current_node": "c",
+            "mapping": {
+                "a": {"message": {"author": {"role": "user"}, "content": {"parts": ["How do I sort?"]}}, "parent": null, "children": ["b", "c"]},
+                "b": {"message": {"author": {"role": "assistant"}, "content": {"parts": ["Use sort()"]}}, "parent": "a", "children": []},
+                "c": {"message": {"author": {"role": "assistant"}, "content": {"parts": ["Use <sort_by>"]}, "metadata": {"model_slug": "gpt-4o"}}, "parent": "a", "children": []}
+            }
+        }]);
+        std::io::Write::write_all(&mut zip, conversations.to_string().as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    cli_in(&dir)
+        .args(["session", "import"])
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Imported 'sorting-in-rust' (3 messages)",
+        ));
+    cli_in(&dir)
+        .args(["session", "show", "sorting-in-rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Messages: 2"))
+        .stdout(predicate::str::contains("Branches: 2"));
+
+    let html = dir.join("sorting.html");
+    cli_in(&dir)
+        .args([
+            "session",
+            "export",
+            "sorting-in-rust",
+            "--format",
+            "html",
+            "-o",
+        ])
+        .arg(&html)
+        .assert()
+        .success();
+    let page = std::fs::read_to_string(&html).unwrap();
+    assert!(page.contains("<h2>Assistant (gpt-4o)</h2>\n<pre>Use &lt;sort_by&gt;</pre>"));
+
+    let markdown = dir.join("sorting.md");
+    cli_in(&dir)
+        .args([
+            "session",
+            "export",
+            "sorting-in-rust",
+            "--format",
+            "markdown",
+            "-o",
+        ])
+        .arg(&markdown)
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(&markdown).unwrap(),
+        "# sorting-in-rust\n\n<!-- message role=\"user\" timestamp=\"0\" -->\n## User\n\n\
+         How do I sort?\n\n<!-- message role=\"assistant\" model=\"gpt-4o\" timestamp=\"0\" -->\n\
+         ## Assistant (gpt-4o)\n\nUse <sort_by>\n"
+    );
+
+    // Importing back never overwrites the original
+    cli_in(&dir)
+        .args(["session", "import"])
+        .arg(&markdown)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Imported 'sorting-in-rust-2' (2 messages)",
+        ));
+    cli_in(&dir)
+        .args(["session", "import", "--name", "copy"])
+        .arg(&html)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'copy' (2 messages)"));
+
+    // A tree whose messages point past the end would break every later read
+    let broken = dir.join("broken.json");
+    std::fs::write(
+        &broken,
+        r#"{"name": "broken", "nodes": [{"parent": 3, "role": "user", "content": "Hi", "timestamp": 1}], "head": 0, "created_at": 1, "updated_at": 1}"#,
+    )
+    .unwrap();
+    cli_in(&dir)
+        .args(["session", "import"])
+        .arg(&broken)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("has messages out of order"));
+    cli_in(&dir).args(["session", "list"]).assert().success();
+
+    let notes = dir.join("notes.txt");
+    std::fs::write(&notes, "just some notes").unwrap();
+    cli_in(&dir)
+        .args(["session", "import"])
+        .arg(&notes)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "no messages found in a known format",
+        ));
+}
+
+#[test]
+fn test_session_metadata_tags_and_list() {
+    let mut server = mockito::Server::new();
+    let _reply = server
+        .mock("POST", "/v1/chat/completions")
+        .with_body(r#"{"id":"t","choices":[{"message":{"content":"Borrow it."}}],"usage":{"prompt_tokens":7,"completion_tokens":3}}"#)
+        .expect(2)
+        .create();
+    let dir = project_dir(
+        "session-metadata",
+        &local_provider_config(&format!("{}/v1", server.url()), false),
+    );
+
+    for session in ["rust", "misc"] {
+        cli_in(&dir)
+            .args([
+                "ask",
+                "How do I share data between threads?",
+                "--session",
+                session,
+            ])
+            .assert()
+            .success();
+    }
+
+    cli_in(&dir)
+        .args(["session", "tag", "rust", "work", "threads"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tagged 'rust': threads, work"));
+    cli_in(&dir)
+        .args([
+            "session",
+            "describe",
+            "rust",
+            "Concurrency notes",
+            "--title",
+            "Threads",
+        ])
+        .assert()
+        .success();
+
+    cli_in(&dir)
+        .args(["session", "show", "rust", "--tree"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Title: Threads"))
+        .stdout(predicate::str::contains("Description: Concurrency notes"))
+        .stdout(predicate::str::contains("Tags: threads, work"))
+        .stdout(predicate::str::contains("Models: llama3 (local)"))
+        .stdout(predicate::str::contains(
+            "#1 assistant (llama3): Borrow it.",
+        ));
+
+    cli_in(&dir)
+        .args(["session", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SESSION"))
+        .stdout(predicate::str::contains(
+            "How do I share data between threads?",
+        ))
+        .stdout(predicate::str::contains("threads, work"));
+    cli_in(&dir)
+        .args(["session", "list", "--tag", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Threads"))
+        .stdout(predicate::str::contains("misc").not());
+
+    let export = dir.join("rust.json");
+    cli_in(&dir)
+        .args(["session", "export", "rust", "-o"])
+        .arg(&export)
+        .assert()
+        .success();
+    let session: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&export).unwrap()).unwrap();
+    let reply = &session["nodes"][1];
+    assert_eq!(reply["model"], "llama3");
+    assert_eq!(reply["provider"], "local");
+    assert_eq!(reply["usage"]["output"], 3);
+    assert!(reply["latency_ms"].is_u64());
+
+    cli_in(&dir)
+        .args(["session", "tag", "rust", "work", "--remove"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tagged 'rust': threads"));
+}